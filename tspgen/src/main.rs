@@ -1,9 +1,24 @@
-use rand::{distributions::Uniform, prelude::Distribution, SeedableRng};
+use rand::{distributions::Uniform, prelude::Distribution, Rng, SeedableRng};
 use rand_chacha::{self, ChaCha8Rng};
 use getopt::Opt;
-use std::{fs, io::Write};
+use std::{fs, io::{self, Write}};
+use tsptools::helpers::{generate_distance_matrix, Node};
 
-fn fill_graph(distance_matrix:  &mut Vec<Vec<i32>>, density: f64, seed: Option<u64>, allow_loops: bool, is_directed: bool) {
+mod points;
+
+use points::{circle_points, clustered_points, grid_points, uniform_points, write_tsplib};
+
+const USAGE: &str = "Usage: tspgen -s <size> [-o <filename>] [-r <seed>] [-m <mode>]
+  -m matrix    random weights in 1..100 (default): [-d <density>] [-c] [-l]
+  -m uniform   cities uniform in a square
+  -m clustered Gaussian clusters of cities: [-k <clusters>, default size / 100]
+  -m grid      cities on a grid: [-j <jitter>, fraction of the spacing, default 0.1]
+  -m circle    cities evenly spaced on a circle
+ the geometric modes take [-b <side of the square>, default 1000000]
+ [-w <EDGE_WEIGHT_TYPE>, default EUC_2D] and [-f tsplib|matrix, default tsplib]";
+
+#[allow(clippy::needless_range_loop)]
+fn fill_graph(distance_matrix:  &mut [Vec<i32>], density: f64, seed: Option<u64>, allow_loops: bool, is_directed: bool) {
     let mut rng = match seed {
         Some(seed) => ChaCha8Rng::seed_from_u64(seed),
         None => ChaCha8Rng::from_entropy(),
@@ -22,7 +37,7 @@ fn fill_graph(distance_matrix:  &mut Vec<Vec<i32>>, density: f64, seed: Option<u
             }
         }
     } else {
-        let mut edges = ((density * distance_matrix.len() as f64 * distance_matrix[0].len() as f64)) as i32;
+        let mut edges = (density * distance_matrix.len() as f64 * distance_matrix[0].len() as f64) as i32;
         while edges > 0 {
             let row = Uniform::from(0..distance_matrix.len()).sample(&mut rng);
             let col = Uniform::from(0..distance_matrix[row].len()).sample(&mut rng);
@@ -45,9 +60,28 @@ fn fill_graph(distance_matrix:  &mut Vec<Vec<i32>>, density: f64, seed: Option<u
     }
 }
 
+fn write_matrix<W: Write>(out: &mut W, distance_matrix: &[Vec<i32>]) -> io::Result<()> {
+    writeln!(out, "{}", distance_matrix.len())?;
+    for row in distance_matrix {
+        let weights: Vec<String> = row.iter().map(|weight| weight.to_string()).collect();
+        writeln!(out, "{}", weights.join("\t"))?;
+    }
+    Ok(())
+}
+
+fn generate_points(mode: &str, size: usize, rng: &mut ChaCha8Rng, bound: f32, clusters: Option<usize>, jitter: f32) -> Vec<Node> {
+    match mode {
+        "uniform" => uniform_points(size, bound, rng),
+        "clustered" => clustered_points(size, clusters.unwrap_or(size / 100), bound, rng),
+        "grid" => grid_points(size, jitter, bound, rng),
+        "circle" => circle_points(size, bound),
+        _ => panic!("Unknown mode {}\n{}", mode, USAGE),
+    }
+}
+
 fn main() {
     let args = std::env::args().collect::<Vec<String>>();
-    let mut opts = getopt::Parser::new(&args, "s:d:r:clo:h");
+    let mut opts = getopt::Parser::new(&args, "s:d:r:clo:m:w:b:k:j:f:h");
     
     let mut size = 0;
     let mut density = 1.0;
@@ -55,6 +89,12 @@ fn main() {
     let mut allow_loops = false;
     let mut is_directed = false;
     let mut filename = None;
+    let mut mode = String::from("matrix");
+    let mut weight_type = String::from("EUC_2D");
+    let mut bound = 1000000.0;
+    let mut clusters = None;
+    let mut jitter = 0.1;
+    let mut format = String::from("tsplib");
     let mut help = false;
     
     loop{
@@ -67,6 +107,12 @@ fn main() {
                 Opt('d', Some(arg)) => density = arg.parse::<f64>().unwrap(),
                 Opt('l', _) => is_directed = true,
                 Opt('o', Some(arg)) => filename = Some(arg),
+                Opt('m', Some(arg)) => mode = arg,
+                Opt('w', Some(arg)) => weight_type = arg,
+                Opt('b', Some(arg)) => bound = arg.parse::<f32>().unwrap(),
+                Opt('k', Some(arg)) => clusters = Some(arg.parse::<usize>().unwrap()),
+                Opt('j', Some(arg)) => jitter = arg.parse::<f32>().unwrap(),
+                Opt('f', Some(arg)) => format = arg,
                 Opt('h', _) => help = true,
                 _ => panic!("Invalid arguments"),
             }
@@ -74,40 +120,53 @@ fn main() {
         }
     }
     if help {
-        println!("Usage: tspgen -s <size> [-o <filename>] [-d <density>] [-r <seed>] [-c] [-l]");
+        println!("{}", USAGE);
         return;
     }
     if size == 0 {
-        panic!("Usage: tspgen -s <size> [-o <filename>] [-d <density>] [-r <seed>] [-c] [-l]");
+        panic!("{}", USAGE);
+    }
+    if jitter.is_nan() || jitter < 0.0 {
+        panic!("Jitter must not be negative\n{}", USAGE);
     }
-    let mut distance_matrix = vec![vec![0; size]; size];
-    fill_graph(&mut distance_matrix, density, seed, allow_loops, is_directed);
 
-    if filename != None {
-        let mut file = fs::File::create(filename.as_ref().unwrap()).unwrap();
-        writeln!(file, "{}", size).unwrap();
-        for row in 0..distance_matrix.len() {
-            for col in 0..distance_matrix[row].len() {
-                if col != distance_matrix[row].len() - 1 {
-                    write!(file, "{}\t", distance_matrix[row][col]).unwrap();
-                } else {
-                    write!(file, "{}", distance_matrix[row][col]).unwrap();
-                }
-            }
-            writeln!(file).unwrap();
-        }
-        println!("Graph written to {}", filename.as_ref().unwrap());
+    let mut out: Box<dyn Write> = match &filename {
+        Some(filename) => Box::new(io::BufWriter::new(fs::File::create(filename).unwrap())),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    if mode == "matrix" {
+        let mut distance_matrix = vec![vec![0; size]; size];
+        fill_graph(&mut distance_matrix, density, seed, allow_loops, is_directed);
+        write_matrix(&mut out, &distance_matrix).unwrap();
     } else {
-        println!("{}", size);
-        for row in 0..distance_matrix.len() {
-            for col in 0..distance_matrix[row].len() {
-                if col != distance_matrix[row].len() - 1 {
-                    print!("{}\t", distance_matrix[row][col]);
-                } else {
-                    print!("{}", distance_matrix[row][col]);
-                }
+        let seed = seed.unwrap_or_else(|| ChaCha8Rng::from_entropy().gen());
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let nodes = generate_points(&mode, size, &mut rng, bound, clusters, jitter);
+
+        //the distance functions of tsptools decide which weight types are supported
+        if let Err(e) = generate_distance_matrix(&nodes[..size.min(2)], &weight_type) {
+            panic!("{}: {}", e, weight_type);
+        }
+        match format.as_str() {
+            "tsplib" => {
+                let name = match &filename {
+                    Some(filename) => std::path::Path::new(filename).file_stem().unwrap().to_string_lossy().into_owned(),
+                    None => format!("{}{}", mode, size),
+                };
+                let comment = format!("tspgen {} mode, seed {}", mode, seed);
+                write_tsplib(&mut out, &name, &comment, &weight_type, &nodes).unwrap();
+            }
+            "matrix" => {
+                let distance_matrix = generate_distance_matrix(&nodes, &weight_type).unwrap();
+                write_matrix(&mut out, &distance_matrix).unwrap();
             }
-            println!();
+            _ => panic!("Unknown format {}\n{}", format, USAGE),
         }
     }
-}
\ No newline at end of file
+    out.flush().unwrap();
+
+    if let Some(filename) = filename {
+        println!("Graph written to {}", filename);
+    }
+}
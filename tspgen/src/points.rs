@@ -0,0 +1,195 @@
+use rand::{distributions::Uniform, prelude::Distribution, Rng};
+use std::{f32::consts::PI, io::Write};
+use tsptools::helpers::Node;
+
+/// Cities uniform in the square [0, bound), with integer coordinates as in DIMACS portgen
+pub fn uniform_points<R: Rng>(size: usize, bound: f32, rng: &mut R) -> Vec<Node> {
+    let coordinate = Uniform::from(0..bound.max(1.0) as u32);
+    (0..size)
+        .map(|i| {
+            node(
+                i,
+                coordinate.sample(rng) as f32,
+                coordinate.sample(rng) as f32,
+            )
+        })
+        .collect()
+}
+
+/// Gaussian clusters around centers uniform in the square, as in DIMACS portcgen: every city
+/// picks a random center and is displaced by a normal deviate of `bound / sqrt(size)` in each
+/// coordinate, rounded to integers
+pub fn clustered_points<R: Rng>(
+    size: usize,
+    clusters: usize,
+    bound: f32,
+    rng: &mut R,
+) -> Vec<Node> {
+    let centers = uniform_points(clusters.max(1), bound, rng);
+    let sigma = bound / (size.max(1) as f32).sqrt();
+    let center = Uniform::from(0..centers.len());
+    (0..size)
+        .map(|i| {
+            let c = &centers[center.sample(rng)];
+            let (dx, dy) = normal_pair(rng);
+            node(i, (c.x + sigma * dx).round(), (c.y + sigma * dy).round())
+        })
+        .collect()
+}
+
+/// Row by row on a square grid spanning the square, every city moved by up to `jitter` times
+/// the grid spacing in each coordinate. `jitter` must not be negative.
+pub fn grid_points<R: Rng>(size: usize, jitter: f32, bound: f32, rng: &mut R) -> Vec<Node> {
+    let side = (size as f64).sqrt().ceil().max(1.0) as usize;
+    let spacing = bound / side as f32;
+    let offset = Uniform::new_inclusive(-jitter, jitter);
+    (0..size)
+        .map(|i| {
+            let (row, col) = (i / side, i % side);
+            let mut jitter = || match jitter > 0.0 {
+                true => spacing * offset.sample(rng),
+                false => 0.0,
+            };
+            let x = spacing * (col as f32 + 0.5) + jitter();
+            let y = spacing * (row as f32 + 0.5) + jitter();
+            node(i, x, y)
+        })
+        .collect()
+}
+
+/// Evenly spaced on the circle inscribed in the square, so the optimal tour follows the circle
+pub fn circle_points(size: usize, bound: f32) -> Vec<Node> {
+    let radius = bound / 2.0;
+    (0..size)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / size as f32;
+            node(
+                i,
+                radius + radius * angle.cos(),
+                radius + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+fn node(pos: usize, x: f32, y: f32) -> Node {
+    Node {
+        pos,
+        id: pos as u32 + 1,
+        x,
+        y,
+    }
+}
+
+//Box-Muller transform, rand has no normal distribution without rand_distr
+fn normal_pair<R: Rng>(rng: &mut R) -> (f32, f32) {
+    let u1: f32 = 1.0 - rng.gen::<f32>();
+    let u2: f32 = rng.gen();
+    let r = (-2.0 * u1.ln()).sqrt();
+    (r * (2.0 * PI * u2).cos(), r * (2.0 * PI * u2).sin())
+}
+
+/// TSPLIB file with a NODE_COORD_SECTION, readable by `tsptools::parsers::parse_tsp_file`
+pub fn write_tsplib<W: Write>(
+    out: &mut W,
+    name: &str,
+    comment: &str,
+    weight_type: &str,
+    nodes: &[Node],
+) -> std::io::Result<()> {
+    writeln!(out, "NAME : {}", name)?;
+    writeln!(out, "COMMENT : {}", comment)?;
+    writeln!(out, "TYPE : TSP")?;
+    writeln!(out, "DIMENSION : {}", nodes.len())?;
+    writeln!(out, "EDGE_WEIGHT_TYPE : {}", weight_type)?;
+    writeln!(out, "NODE_COORD_SECTION")?;
+    for node in nodes {
+        writeln!(out, "{} {} {}", node.id, node.x, node.y)?;
+    }
+    writeln!(out, "EOF")
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use tsptools::{helpers::generate_distance_matrix, parsers::parse_tsp_file};
+
+    use super::*;
+
+    const BOUND: f32 = 1000.0;
+
+    fn generate(mode: &str, seed: u64) -> Vec<Node> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        match mode {
+            "uniform" => uniform_points(100, BOUND, &mut rng),
+            "clustered" => clustered_points(100, 4, BOUND, &mut rng),
+            "grid" => grid_points(100, 0.25, BOUND, &mut rng),
+            _ => circle_points(100, BOUND),
+        }
+    }
+
+    fn coordinates(nodes: &[Node]) -> Vec<(f32, f32)> {
+        nodes.iter().map(|node| (node.x, node.y)).collect()
+    }
+
+    #[test]
+    fn modes_stay_in_bounds_and_follow_the_seed() {
+        for mode in ["uniform", "clustered", "grid", "circle"] {
+            let nodes = generate(mode, 1);
+            assert_eq!(nodes.len(), 100);
+            assert!(nodes
+                .iter()
+                .enumerate()
+                .all(|(i, node)| node.pos == i && node.id == i as u32 + 1));
+
+            //clusters are not clipped, but 10 standard deviations are never exceeded
+            let margin = match mode {
+                "clustered" => 10.0 * BOUND / 100f32.sqrt(),
+                _ => 0.0,
+            };
+            for node in &nodes {
+                for coordinate in [node.x, node.y] {
+                    assert!(
+                        coordinate >= -margin && coordinate <= BOUND + margin,
+                        "{}",
+                        mode
+                    );
+                }
+                if mode == "uniform" {
+                    assert!(node.x < BOUND && node.x.fract() == 0.0);
+                }
+            }
+
+            assert_eq!(coordinates(&nodes), coordinates(&generate(mode, 1)));
+            if mode != "circle" {
+                assert_ne!(coordinates(&nodes), coordinates(&generate(mode, 2)));
+            }
+        }
+
+        //without jitter the grid is regular
+        let grid = grid_points(9, 0.0, 300.0, &mut ChaCha8Rng::seed_from_u64(1));
+        assert_eq!((grid[4].x, grid[4].y), (150.0, 150.0));
+        assert_eq!((grid[8].x, grid[8].y), (250.0, 250.0));
+    }
+
+    #[test]
+    fn tsplib_output_parses_back() {
+        let path = std::env::temp_dir().join(format!("tspgen_test_{}.tsp", std::process::id()));
+        for weight_type in ["EUC_2D", "CEIL_2D", "MAN_2D"] {
+            let nodes = generate("grid", 3);
+            let mut file = std::fs::File::create(&path).unwrap();
+            write_tsplib(&mut file, "grid100", "tspgen test", weight_type, &nodes).unwrap();
+            drop(file);
+
+            let parsed = parse_tsp_file(path.to_str().unwrap()).unwrap();
+            assert_eq!(parsed.name, "grid100");
+            assert_eq!(parsed.dimension, 100);
+            assert_eq!(
+                parsed.distance_matrix,
+                generate_distance_matrix(&nodes, weight_type).unwrap()
+            );
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
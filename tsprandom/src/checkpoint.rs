@@ -0,0 +1,413 @@
+//! Checkpoints of a sampling run. Sample `i` draws from stream `i` of a generator seeded from
+//! the master seed, so the state of the random number generators is just the seed and the index
+//! of the next sample. The rest of the file is the visited starting points and the optima found.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, BufRead, BufReader, BufWriter, Lines, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    cli::SampleArgs,
+    dedup::{Dedup, VisitedSet},
+    output::{Output, StartingPointsDump, StartingPointsWriter},
+    sampler::Sampling,
+    shards::{Shards, SHARD_COUNT},
+    stable_hash::StableHasher,
+    LocalOptimum,
+};
+
+const HEADER: &str = "tsprandom checkpoint 2";
+
+/// Hash of the distance matrix, so that a checkpoint is not resumed on another instance
+pub fn fingerprint(distance_matrix: &[Vec<i32>]) -> u64 {
+    let mut hasher = StableHasher::with_keys(0, 0);
+    hasher.write_usize(distance_matrix.len());
+    for row in distance_matrix {
+        hasher.write_usize(row.len());
+        for &distance in row {
+            hasher.write_i32(distance);
+        }
+    }
+    hasher.finish()
+}
+
+/// Settings a resumed run has to share with the run that wrote the checkpoint, and where its
+/// files are
+pub struct RunInfo {
+    pub instance: String,
+    pub fingerprint: u64,
+    pub alg: String,
+    pub preserve_first: bool,
+    pub max_retries: usize,
+    pub dedup: Dedup,
+    pub seed: u64,
+    pub out_dir: PathBuf,
+    pub timestamp: String,
+    pub starting_points: StartingPointsDump,
+    /// Bytes of the starting points file written up to the checkpoint
+    pub starting_points_len: u64,
+}
+
+impl RunInfo {
+    pub fn new(args: &SampleArgs, fingerprint: u64, seed: u64, output: &Output) -> Self {
+        RunInfo {
+            instance: args.path.clone(),
+            fingerprint,
+            alg: args.alg.clone(),
+            preserve_first: args.preserve_first,
+            max_retries: args.max_retries,
+            dedup: args.dedup,
+            seed,
+            out_dir: output.dir().to_path_buf(),
+            timestamp: output.timestamp().to_owned(),
+            starting_points: args.starting_points,
+            starting_points_len: 0,
+        }
+    }
+
+    /// Fails on the first setting of `args` that would change the samples of the run
+    pub fn check(&self, args: &SampleArgs, fingerprint: u64) -> Result<(), String> {
+        fn differs(option: &str, value: &dyn Display) -> Result<(), String> {
+            Err(format!(
+                "--{} differs from the resumed run, which used {}",
+                option, value
+            ))
+        }
+
+        if fingerprint != self.fingerprint {
+            return Err(format!(
+                "{} is not the instance of the resumed run ({})",
+                args.path, self.instance
+            ));
+        }
+        if args.alg != self.alg {
+            return differs("alg", &self.alg);
+        }
+        if args.preserve_first != self.preserve_first {
+            return differs("preserve-first", &self.preserve_first);
+        }
+        if args.max_retries != self.max_retries {
+            return differs("max-retries", &self.max_retries);
+        }
+        if args.dedup != self.dedup {
+            return differs("dedup", &self.dedup);
+        }
+        if args.seed.is_some_and(|seed| seed != self.seed) {
+            return differs("seed", &self.seed);
+        }
+        if args.starting_points != self.starting_points {
+            return differs("starting-points", &self.starting_points.name());
+        }
+        Ok(())
+    }
+}
+
+/// Saves checkpoints of a run, replacing the previous one
+pub struct Checkpointer {
+    path: PathBuf,
+    run: RunInfo,
+    every: Duration,
+    last: Instant,
+}
+
+impl Checkpointer {
+    pub fn new(path: PathBuf, run: RunInfo, every: Duration) -> Self {
+        Checkpointer {
+            path,
+            run,
+            every,
+            last: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Saves a checkpoint if the last one is older than the interval
+    pub fn save_if_due(
+        &mut self,
+        sampling: &Sampling,
+        starting_points: Option<&mut StartingPointsWriter>,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.last.elapsed() < self.every {
+            return Ok(());
+        }
+        self.save(sampling, starting_points)
+    }
+
+    /// Flushes the starting points, then writes the checkpoint next to the old one and renames
+    /// it, so that an interruption never leaves a partial checkpoint behind
+    pub fn save(
+        &mut self,
+        sampling: &Sampling,
+        starting_points: Option<&mut StartingPointsWriter>,
+    ) -> Result<(), Box<dyn Error>> {
+        let error = |e: io::Error| format!("Could not write {}: {}", self.path.display(), e);
+        if let Some(writer) = starting_points {
+            self.run.starting_points_len = writer.checkpoint().map_err(error)?;
+        }
+
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let write = || -> io::Result<()> {
+            let mut file = BufWriter::new(File::create(&tmp_path)?);
+            write_checkpoint(&mut file, &self.run, sampling)?;
+            file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            fs::rename(&tmp_path, &self.path)
+        };
+        write().map_err(error)?;
+        self.last = Instant::now();
+        Ok(())
+    }
+}
+
+pub fn load(path: &Path) -> Result<(RunInfo, Sampling), Box<dyn Error>> {
+    let error = |e: String| format!("Could not read checkpoint {}: {}", path.display(), e);
+    let file = File::open(path).map_err(|e| error(e.to_string()))?;
+    let checkpoint = read_checkpoint(&mut LineReader::new(BufReader::new(file))).map_err(error)?;
+    Ok(checkpoint)
+}
+
+fn write_checkpoint<W: Write>(w: &mut W, run: &RunInfo, sampling: &Sampling) -> io::Result<()> {
+    writeln!(w, "{}", HEADER)?;
+    writeln!(w, "instance {}", run.instance)?;
+    writeln!(w, "fingerprint {:016x}", run.fingerprint)?;
+    writeln!(w, "alg {}", run.alg)?;
+    writeln!(w, "preserve_first {}", run.preserve_first)?;
+    writeln!(w, "max_retries {}", run.max_retries)?;
+    writeln!(w, "dedup {}", run.dedup)?;
+    writeln!(w, "seed {}", run.seed)?;
+    writeln!(w, "out_dir {}", run.out_dir.display())?;
+    writeln!(w, "timestamp {}", run.timestamp)?;
+    writeln!(w, "starting_points {}", run.starting_points.name())?;
+    writeln!(w, "starting_points_len {}", run.starting_points_len)?;
+    writeln!(w, "next_index {}", sampling.next_index)?;
+    writeln!(w, "samples {}", sampling.samples)?;
+    writeln!(w, "lost {}", sampling.lost)?;
+
+    for (visited, optima) in sampling
+        .visited_starting
+        .iter()
+        .zip(sampling.local_minimums.iter())
+    {
+        visited.write_to(w)?;
+        writeln!(w, "optima {}", optima.len())?;
+        for (key, optimum) in optima {
+            let variants: Vec<String> = optimum
+                .variants
+                .iter()
+                .map(|(tour, count)| format!("{}:{}", format_tour(tour), count))
+                .collect();
+            writeln!(
+                w,
+                "{};{};{};{}",
+                format_tour(key),
+                optimum.len,
+                optimum.related_starting_points,
+                variants.join(",")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn read_checkpoint<R: BufRead>(reader: &mut LineReader<R>) -> Result<(RunInfo, Sampling), String> {
+    if reader.next_line()? != HEADER {
+        return Err(String::from("not a tsprandom checkpoint"));
+    }
+    let run = RunInfo {
+        instance: reader.field("instance")?,
+        fingerprint: {
+            let fingerprint: String = reader.field("fingerprint")?;
+            u64::from_str_radix(&fingerprint, 16).map_err(|_| reader.invalid())?
+        },
+        alg: reader.field("alg")?,
+        preserve_first: reader.field("preserve_first")?,
+        max_retries: reader.field("max_retries")?,
+        dedup: reader.field("dedup")?,
+        seed: reader.field("seed")?,
+        out_dir: reader.field("out_dir")?,
+        timestamp: reader.field("timestamp")?,
+        starting_points: reader.field("starting_points")?,
+        starting_points_len: reader.field("starting_points_len")?,
+    };
+    let next_index = reader.field("next_index")?;
+    let samples = reader.field("samples")?;
+    let lost = reader.field("lost")?;
+
+    let mut visited_starting = Vec::with_capacity(SHARD_COUNT);
+    let mut local_minimums = Vec::with_capacity(SHARD_COUNT);
+    for _ in 0..SHARD_COUNT {
+        visited_starting.push(VisitedSet::read_from(reader)?);
+        let count: usize = reader.field("optima")?;
+        let mut optima = FxHashMap::default();
+        for _ in 0..count {
+            let line = reader.next_line()?;
+            let (key, optimum) = parse_optimum(&line).ok_or_else(|| reader.invalid())?;
+            optima.insert(key, optimum);
+        }
+        local_minimums.push(optima);
+    }
+
+    let sampling = Sampling {
+        local_minimums: Shards::from_vec(local_minimums),
+        visited_starting: Shards::from_vec(visited_starting),
+        next_index,
+        samples,
+        lost,
+        exhausted: false,
+        timed_out: false,
+        interrupted: false,
+    };
+    Ok((run, sampling))
+}
+
+fn parse_optimum(line: &str) -> Option<(Vec<u16>, LocalOptimum)> {
+    let mut fields = line.split(';');
+    let key = parse_tour(fields.next()?)?;
+    let len = fields.next()?.parse().ok()?;
+    let related_starting_points = fields.next()?.parse().ok()?;
+    let mut variants = FxHashMap::default();
+    for variant in fields.next()?.split(',').filter(|v| !v.is_empty()) {
+        let (tour, count) = variant.split_once(':')?;
+        variants.insert(parse_tour(tour)?, count.parse().ok()?);
+    }
+    let optimum = LocalOptimum {
+        len,
+        related_starting_points,
+        variants,
+    };
+    Some((key, optimum))
+}
+
+/// Cities separated by spaces
+pub fn format_tour(tour: &[u16]) -> String {
+    tour.iter()
+        .map(|city| city.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn parse_tour(s: &str) -> Option<Vec<u16>> {
+    s.split(' ').map(|city| city.parse().ok()).collect()
+}
+
+/// Lines of a checkpoint, counted for the error messages
+pub struct LineReader<R> {
+    lines: Lines<R>,
+    line: usize,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R) -> Self {
+        LineReader {
+            lines: reader.lines(),
+            line: 0,
+        }
+    }
+
+    pub fn next_line(&mut self) -> Result<String, String> {
+        self.line += 1;
+        match self.lines.next() {
+            Some(line) => line.map_err(|e| e.to_string()),
+            None => Err(format!("line {}: unexpected end of file", self.line)),
+        }
+    }
+
+    /// Value of a `key value` line
+    pub fn field<T: FromStr>(&mut self, key: &str) -> Result<T, String> {
+        let line = self.next_line()?;
+        match line.split_once(' ') {
+            Some((k, value)) if k == key => value.parse().map_err(|_| self.invalid()),
+            _ => Err(format!("line {}: expected {}", self.line, key)),
+        }
+    }
+
+    pub fn invalid(&self) -> String {
+        format!("line {}: invalid value", self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(dedup: Dedup) {
+        let mut sampling = Sampling::new(dedup, 1000, 0.01);
+        let starts = [vec![0, 1, 2, 3], vec![0, 2, 1, 3], vec![0, 3, 1, 2]];
+        for start in &starts {
+            sampling.visited_starting.get_mut(start).insert(start);
+        }
+        let key = vec![0, 1, 2, 3];
+        let mut variants = FxHashMap::default();
+        variants.insert(vec![1, 2, 3, 0], 2);
+        variants.insert(vec![0, 1, 2, 3], 1);
+        sampling.local_minimums.get_mut(&key).insert(
+            key.clone(),
+            LocalOptimum {
+                len: 42,
+                related_starting_points: 3,
+                variants,
+            },
+        );
+        sampling.next_index = 5;
+        sampling.samples = 3;
+        sampling.lost = 2;
+
+        let run = RunInfo {
+            instance: String::from("some instance.tsp"),
+            fingerprint: 7,
+            alg: String::from("2opt"),
+            preserve_first: true,
+            max_retries: 10,
+            dedup,
+            seed: 11,
+            out_dir: PathBuf::from("/tmp/out dir"),
+            timestamp: String::from("2024-01-01 10:00:00 +01:00"),
+            starting_points: StartingPointsDump::Gzip,
+            starting_points_len: 123,
+        };
+        let mut file = vec![];
+        write_checkpoint(&mut file, &run, &sampling).unwrap();
+        let (loaded_run, loaded) = read_checkpoint(&mut LineReader::new(file.as_slice())).unwrap();
+
+        assert_eq!(loaded_run.instance, run.instance);
+        assert_eq!(loaded_run.fingerprint, run.fingerprint);
+        assert_eq!(loaded_run.out_dir, run.out_dir);
+        assert_eq!(loaded_run.timestamp, run.timestamp);
+        assert_eq!(loaded_run.starting_points, run.starting_points);
+        assert_eq!(loaded_run.starting_points_len, run.starting_points_len);
+        assert_eq!((loaded.next_index, loaded.samples, loaded.lost), (5, 3, 2));
+        assert!(starts
+            .iter()
+            .all(|start| loaded.visited_starting.get(start).contains(start)));
+        assert!(!loaded
+            .visited_starting
+            .get(&[0, 1, 3, 2])
+            .contains(&[0, 1, 3, 2]));
+        let visited: usize = loaded.visited_starting.iter().map(|v| v.len()).sum();
+        assert_eq!(visited, 3);
+
+        let optimum = &loaded.local_minimums.get(&key)[&key];
+        assert_eq!((optimum.len, optimum.related_starting_points), (42, 3));
+        assert_eq!(optimum.variants[&vec![1, 2, 3, 0]], 2);
+        assert_eq!(optimum.variants.len(), 2);
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        for dedup in Dedup::ALL {
+            round_trip(dedup);
+        }
+    }
+}
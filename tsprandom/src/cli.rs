@@ -0,0 +1,257 @@
+use std::path::PathBuf;
+
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    Args, Parser, Subcommand,
+};
+use tsptools::{solver::SOLVER_NAMES, tour_distance::TourMetric};
+
+use crate::{
+    dedup::Dedup, export::GraphFormat, html::DEFAULT_TOP_N, landscape::Neighbourhood,
+    lon::Perturbation, output::StartingPointsDump,
+};
+
+/// Samples local optima of a TSP instance and analyses the fitness landscape
+#[derive(Debug, Parser)]
+#[command(name = "tsprandom", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run local search from random starting points and save the local optima found
+    Sample(SampleArgs),
+    /// Sample local optima, then build their local optima network
+    Lon(LonArgs),
+    /// Run local search from every tour of a small instance, giving the exact local optima
+    Exhaustive(ExhaustiveArgs),
+    /// Random walks through the move neighbourhoods, measuring how rugged the landscape is
+    Walk(WalkArgs),
+    /// Print the fitness distance correlation of a saved local optima csv
+    Stats(StatsArgs),
+    /// Draw the plots and graph files of a saved local optima csv
+    Plot(PlotArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SampleArgs {
+    /// TSPLIB file, or simple distance matrix if it ends with .txt
+    pub path: String,
+    /// Local search used to reach the optima
+    #[arg(short, long, value_parser = PossibleValuesParser::new(SOLVER_NAMES))]
+    pub alg: String,
+    /// Samples that have to reach a local optimum [default: 10000 without --time-limit]
+    #[arg(short = 'n', long)]
+    pub samples: Option<usize>,
+    /// Stop sampling after this many seconds
+    #[arg(long, value_parser = parse_seconds)]
+    pub time_limit: Option<f64>,
+    /// Attempts to draw a starting point that was not used before
+    #[arg(long, default_value_t = 10000)]
+    pub max_retries: usize,
+    /// Keep city 0 at the start of every tour
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub preserve_first: bool,
+    /// Worker threads, defaults to the available parallelism
+    #[arg(short = 't', long)]
+    pub threads: Option<usize>,
+    /// Seed for the random number generators, random if not given
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+    /// Skip the correlation plot and the html view
+    #[arg(long)]
+    pub no_plot: bool,
+    /// How visited starting points are remembered: full tours, 128-bit hashes or a Bloom filter
+    #[arg(
+        long,
+        default_value = "exact",
+        value_parser = PossibleValuesParser::new(Dedup::ALL.map(|d| d.name()))
+            .map(|s| s.parse::<Dedup>().unwrap())
+    )]
+    pub dedup: Dedup,
+    /// False positive rate of the Bloom filter at the full sample count
+    #[arg(long, default_value_t = 1e-6, value_parser = parse_probability)]
+    pub bloom_fp: f64,
+    /// How the starting points are saved
+    #[arg(
+        long,
+        default_value = "csv",
+        value_parser = PossibleValuesParser::new(StartingPointsDump::ALL.map(|d| d.name()))
+            .map(|s| s.parse::<StartingPointsDump>().unwrap())
+    )]
+    pub starting_points: StartingPointsDump,
+    /// Seconds between two checkpoints of the sampling state
+    #[arg(long, default_value_t = 300.0, value_parser = parse_seconds)]
+    pub checkpoint_every: f64,
+    /// Continue the run of a checkpoint, writing to its output files. --samples counts the
+    /// samples of the whole run, the settings that select the samples have to match.
+    #[arg(long)]
+    pub resume: Option<PathBuf>,
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct LonArgs {
+    #[command(flatten)]
+    pub sample: SampleArgs,
+    /// Kick applied before every escape: double-bridge or swaps:<k>
+    #[arg(long, default_value = "double-bridge", value_parser = parse_perturbation)]
+    pub perturbation: Perturbation,
+    /// Escapes tried from every sampled optimum
+    #[arg(long, default_value_t = 100)]
+    pub escapes: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct ExhaustiveArgs {
+    /// TSPLIB file, or simple distance matrix if it ends with .txt
+    pub path: String,
+    /// Local search applied to every tour
+    #[arg(short, long, value_parser = PossibleValuesParser::new(SOLVER_NAMES))]
+    pub alg: String,
+    /// Start from every tour of a symmetric instance in one direction only
+    #[arg(long)]
+    pub modulo_reversal: bool,
+    /// Worker threads, defaults to the available parallelism
+    #[arg(short = 't', long)]
+    pub threads: Option<usize>,
+    /// Seed for randomized local searches and the bootstrap, random if not given
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+    /// Skip the correlation plot and the html view
+    #[arg(long)]
+    pub no_plot: bool,
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WalkArgs {
+    /// TSPLIB file, or simple distance matrix if it ends with .txt
+    pub path: String,
+    /// Neighbourhoods to walk through, comma separated
+    #[arg(
+        long = "neighbourhood",
+        value_delimiter = ',',
+        default_values = ["swap", "2opt"],
+        value_parser = PossibleValuesParser::new(Neighbourhood::ALL.map(|n| n.name()))
+            .map(|s| s.parse::<Neighbourhood>().unwrap())
+    )]
+    pub neighbourhoods: Vec<Neighbourhood>,
+    /// Independent walks per neighbourhood
+    #[arg(long, default_value_t = 10)]
+    pub walks: usize,
+    /// Random moves of every walk
+    #[arg(long, default_value_t = 100000)]
+    pub steps: usize,
+    /// Local searches from random tours per neighbourhood whose accepted moves are traced,
+    /// 0 to skip them
+    #[arg(long, default_value_t = 100)]
+    pub descents: usize,
+    /// Largest lag of the autocorrelation function
+    #[arg(long, default_value_t = 100)]
+    pub max_lag: usize,
+    /// Tour length changes up to this are flat for the information content
+    #[arg(long, default_value_t = 0.0)]
+    pub epsilon: f64,
+    /// Keep city 0 at the start of every tour
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub preserve_first: bool,
+    /// Worker threads, defaults to the available parallelism
+    #[arg(short = 't', long)]
+    pub threads: Option<usize>,
+    /// Seed of the walks, random if not given
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+    /// Skip the plots
+    #[arg(long)]
+    pub no_plot: bool,
+    /// Directory the results are written to, created if missing
+    #[arg(short, long, default_value = ".")]
+    pub out_dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Local optima csv written by the sample or lon command
+    pub path: PathBuf,
+    /// Seed for the bootstrap, random if not given
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct PlotArgs {
+    /// Local optima csv written by the sample or lon command
+    pub path: PathBuf,
+    /// Prefix of the written files, defaults to the name of the csv
+    #[arg(long)]
+    pub name: Option<String>,
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalysisArgs {
+    /// Distance between tours used for the statistics and graphs
+    #[arg(
+        short,
+        long,
+        default_value = "swap",
+        value_parser = PossibleValuesParser::new(TourMetric::ALL.map(|m| m.name()))
+            .map(|s| s.parse::<TourMetric>().unwrap())
+    )]
+    pub distance: TourMetric,
+    /// Bootstrap resamples for the confidence intervals
+    #[arg(long, default_value_t = 1000)]
+    pub bootstrap: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// Directory the results are written to, created if missing
+    #[arg(short, long, default_value = ".")]
+    pub out_dir: PathBuf,
+    /// Graph formats to write, comma separated
+    #[arg(
+        long = "format",
+        value_delimiter = ',',
+        default_values = ["graphml", "gexf", "dot", "json"],
+        value_parser = PossibleValuesParser::new(GraphFormat::ALL.map(|f| f.extension()))
+            .map(|s| s.parse::<GraphFormat>().unwrap())
+    )]
+    pub formats: Vec<GraphFormat>,
+    /// Number of best optima drawn in the html view
+    #[arg(long, default_value_t = DEFAULT_TOP_N)]
+    pub top_n: usize,
+}
+
+fn parse_perturbation(s: &str) -> Result<Perturbation, String> {
+    s.parse()
+        .map_err(|_| String::from("expected double-bridge or swaps:<count>"))
+}
+
+fn parse_probability(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(p) if p > 0.0 && p < 1.0 => Ok(p),
+        _ => Err(String::from("expected a number between 0 and 1")),
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(seconds),
+        _ => Err(String::from("expected a positive number of seconds")),
+    }
+}
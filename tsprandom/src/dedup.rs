@@ -0,0 +1,271 @@
+use std::{
+    fmt::Display,
+    hash::Hasher,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+use rustc_hash::FxHashSet;
+
+use crate::{
+    checkpoint::{format_tour, parse_tour, LineReader},
+    stable_hash::StableHasher,
+};
+
+/// How visited starting points are remembered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dedup {
+    /// Full tours, no mistakes
+    Exact,
+    /// 128-bit tour hashes, a collision is practically impossible
+    Hash,
+    /// Bloom filter, rejects a small share of unused tours as visited
+    Bloom,
+}
+
+impl Dedup {
+    pub const ALL: [Dedup; 3] = [Dedup::Exact, Dedup::Hash, Dedup::Bloom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::Hash => "hash",
+            Self::Bloom => "bloom",
+        }
+    }
+}
+
+impl FromStr for Dedup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Self::Exact),
+            "hash" => Ok(Self::Hash),
+            "bloom" => Ok(Self::Bloom),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+impl Display for Dedup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Two independent 64-bit hashes of the tour
+pub fn tour_hash(tour: &[u16]) -> u128 {
+    let half = |salt: u64| {
+        let mut hasher = StableHasher::with_keys(0, salt);
+        hasher.write_tour(tour);
+        hasher.finish() as u128
+    };
+    (half(0) << 64) | half(1)
+}
+
+/// Set of visited starting points
+pub enum VisitedSet {
+    Exact(FxHashSet<Vec<u16>>),
+    Hash(FxHashSet<u128>),
+    Bloom(BloomFilter),
+}
+
+impl VisitedSet {
+    /// `expected` insertions and the false positive rate only size the Bloom filter
+    pub fn new(dedup: Dedup, expected: usize, fp_rate: f64) -> Self {
+        match dedup {
+            Dedup::Exact => Self::Exact(FxHashSet::default()),
+            Dedup::Hash => Self::Hash(FxHashSet::default()),
+            Dedup::Bloom => Self::Bloom(BloomFilter::new(expected, fp_rate)),
+        }
+    }
+
+    pub fn contains(&self, tour: &[u16]) -> bool {
+        match self {
+            Self::Exact(set) => set.contains(tour),
+            Self::Hash(set) => set.contains(&tour_hash(tour)),
+            Self::Bloom(filter) => filter.contains(tour_hash(tour)),
+        }
+    }
+
+    /// Returns whether the tour was new
+    pub fn insert(&mut self, tour: &[u16]) -> bool {
+        match self {
+            Self::Exact(set) => set.insert(tour.to_vec()),
+            Self::Hash(set) => set.insert(tour_hash(tour)),
+            Self::Bloom(filter) => filter.insert(tour_hash(tour)),
+        }
+    }
+
+    /// Number of visited tours, false positives of the Bloom filter are not counted
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Exact(set) => set.len(),
+            Self::Hash(set) => set.len(),
+            Self::Bloom(filter) => filter.inserted,
+        }
+    }
+
+    /// Approximate heap memory used, ignoring the allocator's bookkeeping
+    pub fn memory_bytes(&self) -> usize {
+        match self {
+            Self::Exact(set) => set
+                .iter()
+                .map(|tour| tour.capacity() * 2 + std::mem::size_of::<Vec<u16>>())
+                .sum(),
+            Self::Hash(set) => set.capacity() * std::mem::size_of::<u128>(),
+            Self::Bloom(filter) => filter.bits.len() * 8,
+        }
+    }
+
+    /// Writes the set for a checkpoint: a `visited` header line, then one line per entry
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            Self::Exact(set) => {
+                writeln!(w, "visited exact {}", set.len())?;
+                for tour in set {
+                    writeln!(w, "{}", format_tour(tour))?;
+                }
+            }
+            Self::Hash(set) => {
+                writeln!(w, "visited hash {}", set.len())?;
+                for hash in set {
+                    writeln!(w, "{:032x}", hash)?;
+                }
+            }
+            Self::Bloom(filter) => {
+                writeln!(
+                    w,
+                    "visited bloom {} {} {}",
+                    filter.bit_count, filter.hashes, filter.inserted
+                )?;
+                for word in &filter.bits {
+                    writeln!(w, "{:016x}", word)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a set written by [`VisitedSet::write_to`]
+    pub fn read_from<R: BufRead>(reader: &mut LineReader<R>) -> Result<Self, String> {
+        let header = reader.next_line()?;
+        let fields: Vec<&str> = header.split(' ').collect();
+        let number = |i: usize| fields.get(i).and_then(|field| field.parse::<u64>().ok());
+        match (fields.first(), fields.get(1), number(2)) {
+            (Some(&"visited"), Some(&"exact"), Some(count)) => {
+                let mut set = FxHashSet::default();
+                for _ in 0..count {
+                    let line = reader.next_line()?;
+                    set.insert(parse_tour(&line).ok_or_else(|| reader.invalid())?);
+                }
+                Ok(Self::Exact(set))
+            }
+            (Some(&"visited"), Some(&"hash"), Some(count)) => {
+                let mut set = FxHashSet::default();
+                for _ in 0..count {
+                    let line = reader.next_line()?;
+                    set.insert(u128::from_str_radix(&line, 16).map_err(|_| reader.invalid())?);
+                }
+                Ok(Self::Hash(set))
+            }
+            (Some(&"visited"), Some(&"bloom"), Some(bit_count)) if bit_count > 0 => {
+                let (hashes, inserted) = match (number(3), number(4)) {
+                    (Some(hashes), Some(inserted)) if hashes > 0 => {
+                        (hashes as u32, inserted as usize)
+                    }
+                    _ => return Err(reader.invalid()),
+                };
+                let bits = (0..bit_count.div_ceil(64))
+                    .map(|_| {
+                        let line = reader.next_line()?;
+                        u64::from_str_radix(&line, 16).map_err(|_| reader.invalid())
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Self::Bloom(BloomFilter {
+                    bits,
+                    bit_count,
+                    hashes,
+                    inserted,
+                }))
+            }
+            _ => Err(reader.invalid()),
+        }
+    }
+}
+
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    bit_count: u64,
+    hashes: u32,
+    inserted: usize,
+}
+
+impl BloomFilter {
+    /// Filter keeping the false positive rate below `fp_rate` for up to `expected` insertions
+    pub fn new(expected: usize, fp_rate: f64) -> Self {
+        let expected = expected.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-expected * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let hashes = ((bit_count as f64 / expected) * ln2).round().max(1.0) as u32;
+        BloomFilter {
+            bits: vec![0; bit_count.div_ceil(64) as usize],
+            bit_count,
+            hashes,
+            inserted: 0,
+        }
+    }
+
+    /// Bit positions by double hashing
+    fn positions(&self, hash: u128) -> impl Iterator<Item = u64> + '_ {
+        let (h1, h2) = (hash as u64, (hash >> 64) as u64 | 1);
+        (0..self.hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.bit_count)
+    }
+
+    pub fn contains(&self, hash: u128) -> bool {
+        self.positions(hash)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Returns whether the hash was new, false positives included
+    pub fn insert(&mut self, hash: u128) -> bool {
+        let positions: Vec<u64> = self.positions(hash).collect();
+        let mut new = false;
+        for bit in positions {
+            let word = &mut self.bits[(bit / 64) as usize];
+            new |= *word & (1 << (bit % 64)) == 0;
+            *word |= 1 << (bit % 64);
+        }
+        if new {
+            self.inserted += 1;
+        }
+        new
+    }
+
+    /// Expected false positive rate at the current fill
+    pub fn false_positive_rate(&self) -> f64 {
+        let fill = -(self.hashes as f64 * self.inserted as f64 / self.bit_count as f64);
+        (1.0 - fill.exp()).powi(self.hashes as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_filter_has_no_false_negatives() {
+        let mut filter = BloomFilter::new(1000, 0.01);
+        for i in 0..1000u128 {
+            filter.insert(tour_hash(&[i as u16, 1, 2]));
+        }
+        assert!((0..1000u128).all(|i| filter.contains(tour_hash(&[i as u16, 1, 2]))));
+
+        let false_positives = (0..10000u128)
+            .filter(|&i| filter.contains(tour_hash(&[i as u16, 2, 1])))
+            .count();
+        assert!(false_positives < 300, "{}", false_positives);
+        assert!(filter.false_positive_rate() < 0.02);
+    }
+}
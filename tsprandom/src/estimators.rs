@@ -0,0 +1,229 @@
+//! Estimates of the whole landscape from the sampled optima. The abundance of an optimum is the
+//! number of samples that reached it, i.e. its related starting points.
+
+use std::fmt::Display;
+
+/// Two-sided normal quantile of the 95% intervals
+const Z: f64 = 1.959964;
+
+/// Point estimate with its confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub value: f64,
+    pub ci: (f64, f64),
+}
+
+/// Estimators of the total number of local optima, including the ones never reached
+#[derive(Debug, Clone)]
+pub struct OptimaEstimates {
+    pub samples: usize,
+    pub observed: usize,
+    /// Optima reached by exactly one sample
+    pub singletons: usize,
+    /// Optima reached by exactly two samples
+    pub doubletons: usize,
+    /// Bias-corrected Chao1 with a log-normal interval
+    pub chao1: Option<Estimate>,
+    /// First-order jackknife with a normal interval
+    pub jackknife1: Option<Estimate>,
+    pub jackknife2: Option<f64>,
+    /// Schnabel capture-recapture, treating every sample as a capture occasion
+    pub schnabel: Option<Estimate>,
+    pub confidence: f64,
+}
+
+pub fn estimate_optima(abundances: &[usize]) -> OptimaEstimates {
+    let n: usize = abundances.iter().sum();
+    let observed = abundances.iter().filter(|&&a| a > 0).count();
+    let singletons = abundances.iter().filter(|&&a| a == 1).count();
+    let doubletons = abundances.iter().filter(|&&a| a == 2).count();
+
+    OptimaEstimates {
+        samples: n,
+        observed,
+        singletons,
+        doubletons,
+        chao1: chao1(n, observed, singletons, doubletons),
+        jackknife1: jackknife1(n, observed, singletons),
+        jackknife2: (n > 1).then(|| {
+            let (n, f1, f2) = (n as f64, singletons as f64, doubletons as f64);
+            observed as f64 + f1 * (2.0 * n - 3.0) / n - f2 * (n - 2.0).powi(2) / (n * (n - 1.0))
+        }),
+        schnabel: schnabel(abundances, n, observed),
+        confidence: 0.95,
+    }
+}
+
+/// Chao (1987, 2005): the unseen optima are estimated from the singletons and doubletons
+fn chao1(n: usize, observed: usize, f1: usize, f2: usize) -> Option<Estimate> {
+    if n == 0 {
+        return None;
+    }
+    let (k, f1, f2) = ((n - 1) as f64 / n as f64, f1 as f64, f2 as f64);
+    let unseen = k * f1 * (f1 - 1.0) / (2.0 * (f2 + 1.0));
+    let variance = unseen
+        + k * k * f1 * (2.0 * f1 - 1.0).powi(2) / (4.0 * (f2 + 1.0).powi(2))
+        + k * k * f1 * f1 * f2 * (f1 - 1.0).powi(2) / (4.0 * (f2 + 1.0).powi(4));
+
+    let observed = observed as f64;
+    let ci = if unseen > 0.0 {
+        let c = (Z * (1.0 + variance / (unseen * unseen)).ln().sqrt()).exp();
+        (observed + unseen / c, observed + unseen * c)
+    } else {
+        (observed, observed)
+    };
+    Some(Estimate {
+        value: observed + unseen,
+        ci,
+    })
+}
+
+/// Leave-one-sample-out jackknife, only leaving out a singleton loses an optimum
+fn jackknife1(n: usize, observed: usize, f1: usize) -> Option<Estimate> {
+    if n == 0 {
+        return None;
+    }
+    let (n, f1, observed) = (n as f64, f1 as f64, observed as f64);
+    let value = observed + f1 * (n - 1.0) / n;
+    let half = Z * ((n - 1.0) / n * f1 * (1.0 - f1 / n)).sqrt();
+    Some(Estimate {
+        value,
+        ci: ((value - half).max(observed), value + half),
+    })
+}
+
+/// Schnabel estimate `sum(C_t * M_t) / sum(R_t)` with one capture per occasion. The optima marked
+/// before each sample depend on the sample order, which is random, so their sum is replaced by its
+/// expectation: the first of `a` captures among `n` lands at position `(n - a) / (a + 1)` on average.
+/// It assumes basins of equal size and underestimates when they differ, it is never reported below
+/// the observed optima.
+fn schnabel(abundances: &[usize], n: usize, observed: usize) -> Option<Estimate> {
+    let recaptures = (n - observed) as f64;
+    if recaptures == 0.0 {
+        return None;
+    }
+    let n = n as f64;
+    let marked: f64 = abundances
+        .iter()
+        .filter(|&&a| a > 0)
+        .map(|&a| n - 1.0 - (n - a as f64) / (a as f64 + 1.0))
+        .sum();
+
+    //normal interval of the recaptures, the upper bound is unbounded for very few of them
+    let half = Z * recaptures.sqrt();
+    let high = match recaptures > half {
+        true => marked / (recaptures - half),
+        false => f64::INFINITY,
+    };
+    let observed = observed as f64;
+    Some(Estimate {
+        value: (marked / recaptures).max(observed),
+        ci: (
+            (marked / (recaptures + half)).max(observed),
+            high.max(observed),
+        ),
+    })
+}
+
+/// Wilson score interval of a proportion
+pub fn wilson_interval(successes: usize, trials: usize) -> Option<(f64, f64)> {
+    if trials == 0 {
+        return None;
+    }
+    let (n, p) = (trials as f64, successes as f64 / trials as f64);
+    let z2 = Z * Z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half = Z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+    Some(((center - half).max(0.0), (center + half).min(1.0)))
+}
+
+/// Share of the samples in every basin with its confidence interval
+pub fn basin_shares(abundances: &[usize]) -> Vec<Estimate> {
+    let n: usize = abundances.iter().sum();
+    abundances
+        .iter()
+        .map(|&a| Estimate {
+            value: a as f64 / n.max(1) as f64,
+            ci: wilson_interval(a, n).unwrap_or((0.0, 0.0)),
+        })
+        .collect()
+}
+
+fn fmt_estimate(estimate: Option<Estimate>) -> String {
+    match estimate {
+        Some(Estimate {
+            value,
+            ci: (low, high),
+        }) => format!("{:.1} [{:.1}, {:.1}]", value, low, high),
+        None => String::from("n/a"),
+    }
+}
+
+impl Display for OptimaEstimates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Observed local optima: {} in {} samples ({} singletons, {} doubletons)",
+            self.observed, self.samples, self.singletons, self.doubletons
+        )?;
+        writeln!(
+            f,
+            "Estimated local optima ({}% CI):",
+            (self.confidence * 100.0).round()
+        )?;
+        writeln!(f, "  Chao1: {}", fmt_estimate(self.chao1))?;
+        writeln!(
+            f,
+            "  Jackknife: {}, second order: {}",
+            fmt_estimate(self.jackknife1),
+            match self.jackknife2 {
+                Some(value) => format!("{:.1}", value),
+                None => String::from("n/a"),
+            }
+        )?;
+        write!(f, "  Schnabel: {}", fmt_estimate(self.schnabel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saturated_samples_estimate_the_observed_optima() {
+        let estimates = estimate_optima(&[50, 30, 20]);
+        assert_eq!(estimates.observed, 3);
+        assert_eq!(
+            estimates.chao1,
+            Some(Estimate {
+                value: 3.0,
+                ci: (3.0, 3.0)
+            })
+        );
+        assert_eq!(estimates.jackknife1.unwrap().value, 3.0);
+        let schnabel = estimates.schnabel.unwrap();
+        assert!((schnabel.value - 3.0).abs() < 0.2, "{:?}", schnabel);
+    }
+
+    #[test]
+    fn singletons_raise_the_estimates() {
+        //20 optima seen once, 5 seen twice and 5 seen often
+        let abundances: Vec<usize> = [vec![1; 20], vec![2; 5], vec![10; 5]].concat();
+        let estimates = estimate_optima(&abundances);
+        let chao1 = estimates.chao1.unwrap();
+        assert!((chao1.value - (30.0 + 79.0 / 80.0 * 20.0 * 19.0 / 12.0)).abs() < 1e-9);
+        assert!(chao1.ci.0 > 30.0 && chao1.ci.0 < chao1.value && chao1.value < chao1.ci.1);
+        assert!(estimates.jackknife1.unwrap().value > 30.0);
+        assert!(estimates.schnabel.unwrap().value >= 30.0);
+        assert!(estimate_optima(&[1, 1, 1]).schnabel.is_none());
+    }
+
+    #[test]
+    fn wilson_interval_contains_the_share() {
+        let (low, high) = wilson_interval(30, 100).unwrap();
+        assert!(low < 0.3 && 0.3 < high);
+        assert!((low - 0.2189).abs() < 1e-3 && (high - 0.3958).abs() < 1e-3);
+        assert_eq!(wilson_interval(0, 10).unwrap().0, 0.0);
+        assert!(wilson_interval(0, 0).is_none());
+    }
+}
@@ -0,0 +1,202 @@
+//! Local search from every tour of a small instance. City 0 stays first, so every cyclic tour is
+//! started from once per direction, or once in total with `modulo_reversal`.
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use rand_chacha::{rand_core::SeedableRng, ChaCha8Rng};
+use rustc_hash::FxHashMap;
+use tsptools::helpers::tour_len;
+
+use crate::{
+    canonical_key,
+    sampler::{local_search, new_solver},
+    LocalOptimum,
+};
+
+/// Largest instance that is enumerated, its 11! tours already take minutes
+pub const MAX_CITIES: usize = 12;
+
+pub struct Enumeration {
+    /// Every local optimum, the related starting points are the exact basin sizes
+    pub local_minimums: FxHashMap<Vec<u16>, LocalOptimum>,
+    /// Starting tours the local search was run from
+    pub tours: usize,
+    /// Shortest tour of the instance and its length
+    pub global_optimum: (Vec<u16>, i32),
+}
+
+impl Enumeration {
+    fn merge(mut self, other: Enumeration) -> Self {
+        for (key, optimum) in other.local_minimums {
+            match self.local_minimums.get_mut(&key) {
+                Some(known) => {
+                    known.related_starting_points += optimum.related_starting_points;
+                    for (variant, count) in optimum.variants {
+                        *known.variants.entry(variant).or_insert(0) += count;
+                    }
+                }
+                None => {
+                    self.local_minimums.insert(key, optimum);
+                }
+            }
+        }
+        self.tours += other.tours;
+        let (tour, len) = &other.global_optimum;
+        if (len, tour) < (&self.global_optimum.1, &self.global_optimum.0) {
+            self.global_optimum = other.global_optimum;
+        }
+        self
+    }
+}
+
+/// Runs the local search from every tour. The local search from tour `i` of the lexicographic
+/// order is seeded from stream `i`, so the result does not depend on the number of threads.
+pub fn enumerate(
+    distance_matrix: &[Vec<i32>],
+    alg: &str,
+    symmetric: bool,
+    modulo_reversal: bool,
+    threads: usize,
+    seed: u64,
+) -> Enumeration {
+    let n = distance_matrix.len();
+    //chunks of the tours sharing the second and third city
+    let chunks = (n - 1) * (n - 2);
+    let chunk_len: usize = (1..=n - 3).product();
+    let next_chunk = AtomicUsize::new(0);
+
+    let results: Vec<Enumeration> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut solver = new_solver(alg, true);
+                    let mut found = Enumeration {
+                        local_minimums: FxHashMap::default(),
+                        tours: 0,
+                        global_optimum: (vec![], i32::MAX),
+                    };
+                    loop {
+                        let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+                        if chunk >= chunks {
+                            break;
+                        }
+                        let mut tour = first_of_chunk(n, chunk);
+                        let mut index = chunk * chunk_len;
+                        loop {
+                            if !modulo_reversal || tour[1] < tour[n - 1] {
+                                let len = tour_len(&tour, distance_matrix);
+                                if (len, &tour) < (found.global_optimum.1, &found.global_optimum.0)
+                                {
+                                    found.global_optimum = (tour.clone(), len);
+                                }
+
+                                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                                rng.set_stream(index as u64);
+                                let (optimum_tour, optimum_len) =
+                                    local_search(solver.as_mut(), &mut rng, &tour, distance_matrix);
+                                let optimum = found
+                                    .local_minimums
+                                    .entry(canonical_key(&optimum_tour, symmetric))
+                                    .or_insert_with(|| LocalOptimum {
+                                        len: optimum_len,
+                                        related_starting_points: 0,
+                                        variants: FxHashMap::default(),
+                                    });
+                                optimum.related_starting_points += 1;
+                                *optimum.variants.entry(optimum_tour).or_insert(0) += 1;
+                                found.tours += 1;
+                            }
+                            index += 1;
+                            if !next_permutation(&mut tour[3..]) {
+                                break;
+                            }
+                        }
+                    }
+                    found
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("Enumeration thread panicked"))
+            .collect()
+    });
+
+    results
+        .into_iter()
+        .reduce(Enumeration::merge)
+        .expect("No enumeration thread")
+}
+
+/// First tour of a chunk in lexicographic order
+fn first_of_chunk(n: usize, chunk: usize) -> Vec<u16> {
+    let mut rest: Vec<u16> = (1..n as u16).collect();
+    let second = rest.remove(chunk / (n - 2));
+    let third = rest.remove(chunk % (n - 2));
+    [0, second, third].into_iter().chain(rest).collect()
+}
+
+/// Rearranges the cities into the next permutation in lexicographic order, false after the last one
+fn next_permutation(cities: &mut [u16]) -> bool {
+    let Some(i) = cities.windows(2).rposition(|w| w[0] < w[1]) else {
+        return false;
+    };
+    let j = cities
+        .iter()
+        .rposition(|&city| city > cities[i])
+        .expect("A larger city follows");
+    cities.swap(i, j);
+    cities[i + 1..].reverse();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::skewed_matrix;
+
+    #[test]
+    fn enumerates_every_tour() {
+        let distance_matrix = skewed_matrix(6, None);
+
+        let basins = |enumeration: &Enumeration| -> i32 {
+            enumeration
+                .local_minimums
+                .values()
+                .map(|optimum| optimum.related_starting_points)
+                .sum()
+        };
+
+        let all = enumerate(&distance_matrix, "2opt", true, false, 3, 1);
+        assert_eq!(all.tours, 120);
+        assert_eq!(basins(&all), 120);
+
+        let halved = enumerate(&distance_matrix, "2opt", true, true, 2, 1);
+        assert_eq!(halved.tours, 60);
+        assert_eq!(basins(&halved), 60);
+        assert_eq!(halved.global_optimum.1, all.global_optimum.1);
+
+        let mut tours = vec![];
+        let mut tour: Vec<u16> = (1..6).collect();
+        loop {
+            tours.push(tour.clone());
+            if !next_permutation(&mut tour) {
+                break;
+            }
+        }
+        assert_eq!(tours.len(), 120);
+        assert!(tours.windows(2).all(|w| w[0] < w[1]));
+        let best = tours
+            .iter()
+            .map(|rest| {
+                let tour: Vec<u16> = [0].into_iter().chain(rest.iter().copied()).collect();
+                tour_len(&tour, &distance_matrix)
+            })
+            .min();
+        assert_eq!(best, Some(all.global_optimum.1));
+    }
+}
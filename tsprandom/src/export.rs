@@ -0,0 +1,555 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use tsptools::tour_distance::TourMetric;
+
+use crate::{lon::Lon, LocalOptimum};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    GraphMl,
+    Gexf,
+    Dot,
+    Json,
+}
+
+impl GraphFormat {
+    pub const ALL: [GraphFormat; 4] = [
+        GraphFormat::GraphMl,
+        GraphFormat::Gexf,
+        GraphFormat::Dot,
+        GraphFormat::Json,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::GraphMl => "graphml",
+            Self::Gexf => "gexf",
+            Self::Dot => "dot",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "graphml" => Ok(Self::GraphMl),
+            "gexf" => Ok(Self::Gexf),
+            "dot" => Ok(Self::Dot),
+            "json" => Ok(Self::Json),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub tour: Vec<u16>,
+    pub tour_len: i32,
+    /// Number of sampled starting points that led to this optimum
+    pub basin_size: i32,
+    pub distance_to_best: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphEdge {
+    pub source: usize,
+    pub target: usize,
+    pub weight: u32,
+}
+
+/// Graph of local optima, node ids are indices into `nodes`
+#[derive(Debug, Clone)]
+pub struct OptimaGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    pub directed: bool,
+    /// Meaning of the edge weights, e.g. `bond_distance` or `transitions`
+    pub edge_attribute: String,
+}
+
+impl OptimaGraph {
+    /// Every optimum connected to the best one by an edge weighted with their distance,
+    /// the layout of the html view
+    pub fn star(local_minimums: &[(Vec<u16>, LocalOptimum)], metric: TourMetric) -> Self {
+        let nodes = nodes_with_distances(
+            local_minimums
+                .iter()
+                .map(|(tour, optimum)| (tour, optimum.len, optimum.related_starting_points)),
+            metric,
+        );
+        let edges = (1..nodes.len())
+            .map(|i| GraphEdge {
+                source: 0,
+                target: i,
+                weight: nodes[i].distance_to_best,
+            })
+            .collect();
+
+        Self {
+            nodes,
+            edges,
+            directed: false,
+            edge_attribute: format!("{}_distance", metric),
+        }
+    }
+
+    /// Local optima network with edges weighted by the number of escapes between optima
+    pub fn from_lon(
+        lon: &Lon,
+        local_minimums: &[(Vec<u16>, LocalOptimum)],
+        metric: TourMetric,
+    ) -> Self {
+        let basin = |i: usize| match local_minimums.get(i) {
+            Some((_, optimum)) => optimum.related_starting_points,
+            None => 0,
+        };
+        let nodes = nodes_with_distances(
+            lon.nodes
+                .iter()
+                .enumerate()
+                .map(|(i, (tour, len))| (tour, *len, basin(i))),
+            metric,
+        );
+
+        let mut edges: Vec<GraphEdge> = lon
+            .edges
+            .iter()
+            .map(|(&(source, target), &weight)| GraphEdge {
+                source,
+                target,
+                weight,
+            })
+            .collect();
+        edges.sort_by_key(|e| (e.source, e.target));
+
+        Self {
+            nodes,
+            edges,
+            directed: true,
+            edge_attribute: String::from("transitions"),
+        }
+    }
+
+    /// The first `n` nodes and the edges between them
+    pub fn top(&self, n: usize) -> Self {
+        let n = n.min(self.nodes.len());
+        Self {
+            nodes: self.nodes[..n].to_vec(),
+            edges: self
+                .edges
+                .iter()
+                .filter(|e| e.source < n && e.target < n)
+                .cloned()
+                .collect(),
+            directed: self.directed,
+            edge_attribute: self.edge_attribute.clone(),
+        }
+    }
+
+    pub fn save(&self, path: &Path, format: GraphFormat) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer, format)?;
+        writer.flush()
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W, format: GraphFormat) -> io::Result<()> {
+        match format {
+            GraphFormat::GraphMl => self.write_graphml(w),
+            GraphFormat::Gexf => self.write_gexf(w),
+            GraphFormat::Dot => self.write_dot(w),
+            GraphFormat::Json => self.write_json(w),
+        }
+    }
+
+    fn write_graphml<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            w,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            w,
+            r#"  <key id="tour" for="node" attr.name="tour" attr.type="string"/>"#
+        )?;
+        for key in ["tour_len", "basin_size", "distance_to_best"] {
+            writeln!(
+                w,
+                r#"  <key id="{0}" for="node" attr.name="{0}" attr.type="int"/>"#,
+                key
+            )?;
+        }
+        writeln!(
+            w,
+            r#"  <key id="weight" for="edge" attr.name="{}" attr.type="int"/>"#,
+            xml_escape(&self.edge_attribute)
+        )?;
+        writeln!(
+            w,
+            r#"  <graph id="local_optima" edgedefault="{}">"#,
+            match self.directed {
+                true => "directed",
+                false => "undirected",
+            }
+        )?;
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(w, r#"    <node id="n{}">"#, i)?;
+            writeln!(w, r#"      <data key="tour">{:?}</data>"#, node.tour)?;
+            writeln!(w, r#"      <data key="tour_len">{}</data>"#, node.tour_len)?;
+            writeln!(
+                w,
+                r#"      <data key="basin_size">{}</data>"#,
+                node.basin_size
+            )?;
+            writeln!(
+                w,
+                r#"      <data key="distance_to_best">{}</data>"#,
+                node.distance_to_best
+            )?;
+            writeln!(w, "    </node>")?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                w,
+                r#"    <edge source="n{}" target="n{}"><data key="weight">{}</data></edge>"#,
+                edge.source, edge.target, edge.weight
+            )?;
+        }
+
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</graphml>")
+    }
+
+    fn write_gexf<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<gexf xmlns="http://gexf.net/1.2" version="1.2">"#)?;
+        writeln!(
+            w,
+            r#"  <graph mode="static" defaultedgetype="{}">"#,
+            match self.directed {
+                true => "directed",
+                false => "undirected",
+            }
+        )?;
+        writeln!(w, r#"    <attributes class="node">"#)?;
+        writeln!(w, r#"      <attribute id="0" title="tour" type="string"/>"#)?;
+        writeln!(
+            w,
+            r#"      <attribute id="1" title="tour_len" type="integer"/>"#
+        )?;
+        writeln!(
+            w,
+            r#"      <attribute id="2" title="basin_size" type="integer"/>"#
+        )?;
+        writeln!(
+            w,
+            r#"      <attribute id="3" title="distance_to_best" type="integer"/>"#
+        )?;
+        writeln!(w, "    </attributes>")?;
+        writeln!(w, r#"    <attributes class="edge">"#)?;
+        writeln!(
+            w,
+            r#"      <attribute id="0" title="{}" type="integer"/>"#,
+            xml_escape(&self.edge_attribute)
+        )?;
+        writeln!(w, "    </attributes>")?;
+
+        writeln!(w, "    <nodes>")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(w, r#"      <node id="{}" label="{}">"#, i, node.tour_len)?;
+            writeln!(w, "        <attvalues>")?;
+            writeln!(
+                w,
+                r#"          <attvalue for="0" value="{:?}"/>"#,
+                node.tour
+            )?;
+            writeln!(
+                w,
+                r#"          <attvalue for="1" value="{}"/>"#,
+                node.tour_len
+            )?;
+            writeln!(
+                w,
+                r#"          <attvalue for="2" value="{}"/>"#,
+                node.basin_size
+            )?;
+            writeln!(
+                w,
+                r#"          <attvalue for="3" value="{}"/>"#,
+                node.distance_to_best
+            )?;
+            writeln!(w, "        </attvalues>")?;
+            writeln!(w, "      </node>")?;
+        }
+        writeln!(w, "    </nodes>")?;
+
+        writeln!(w, "    <edges>")?;
+        for (i, edge) in self.edges.iter().enumerate() {
+            writeln!(
+                w,
+                r#"      <edge id="{}" source="{}" target="{}" weight="{}"><attvalues><attvalue for="0" value="{}"/></attvalues></edge>"#,
+                i, edge.source, edge.target, edge.weight, edge.weight
+            )?;
+        }
+        writeln!(w, "    </edges>")?;
+
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</gexf>")
+    }
+
+    fn write_dot<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let (kind, arrow) = match self.directed {
+            true => ("digraph", "->"),
+            false => ("graph", "--"),
+        };
+        writeln!(w, "{} local_optima {{", kind)?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                w,
+                "  {} [label=\"{}\", tour=\"{:?}\", tour_len={}, basin_size={}, distance_to_best={}];",
+                i, node.tour_len, node.tour, node.tour_len, node.basin_size, node.distance_to_best
+            )?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                w,
+                "  {} {} {} [label=\"{}\", \"{}\"={}];",
+                edge.source,
+                arrow,
+                edge.target,
+                edge.weight,
+                json_escape(&self.edge_attribute),
+                edge.weight
+            )?;
+        }
+        writeln!(w, "}}")
+    }
+
+    /// Node-link JSON as read by networkx's `node_link_graph`
+    fn write_json<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "{{")?;
+        writeln!(w, "  \"directed\": {},", self.directed)?;
+        writeln!(w, "  \"multigraph\": false,")?;
+        writeln!(
+            w,
+            "  \"graph\": {{\"edge_attribute\": \"{}\"}},",
+            json_escape(&self.edge_attribute)
+        )?;
+
+        writeln!(w, "  \"nodes\": [")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            let separator = if i + 1 < self.nodes.len() { "," } else { "" };
+            writeln!(
+                w,
+                "    {{\"id\": {}, \"tour\": {:?}, \"tour_len\": {}, \"basin_size\": {}, \"distance_to_best\": {}}}{}",
+                i, node.tour, node.tour_len, node.basin_size, node.distance_to_best, separator
+            )?;
+        }
+        writeln!(w, "  ],")?;
+
+        writeln!(w, "  \"links\": [")?;
+        for (i, edge) in self.edges.iter().enumerate() {
+            let separator = if i + 1 < self.edges.len() { "," } else { "" };
+            writeln!(
+                w,
+                "    {{\"source\": {}, \"target\": {}, \"{}\": {}}}{}",
+                edge.source,
+                edge.target,
+                json_escape(&self.edge_attribute),
+                edge.weight,
+                separator
+            )?;
+        }
+        writeln!(w, "  ]")?;
+        writeln!(w, "}}")
+    }
+}
+
+/// Builds nodes with their distance to the shortest tour among them
+fn nodes_with_distances<'a, I: Iterator<Item = (&'a Vec<u16>, i32, i32)>>(
+    optima: I,
+    metric: TourMetric,
+) -> Vec<GraphNode> {
+    let mut nodes: Vec<GraphNode> = optima
+        .map(|(tour, tour_len, basin_size)| GraphNode {
+            tour: tour.clone(),
+            tour_len,
+            basin_size,
+            distance_to_best: 0,
+        })
+        .collect();
+
+    if let Some(best) = nodes
+        .iter()
+        .min_by_key(|n| n.tour_len)
+        .map(|n| n.tour.clone())
+    {
+        for node in nodes.iter_mut() {
+            node.distance_to_best = metric.distance(&best, &node.tour);
+        }
+    }
+    nodes
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Also valid inside quoted DOT ids, which escape quotes the same way
+fn json_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hash::FxHashMap;
+
+    fn optimum(len: i32, related_starting_points: i32) -> LocalOptimum {
+        LocalOptimum {
+            len,
+            related_starting_points,
+            variants: FxHashMap::default(),
+        }
+    }
+
+    #[test]
+    fn star_graph_to_dot() {
+        let local_minimums = vec![
+            (vec![0, 1, 2, 3], optimum(10, 5)),
+            (vec![0, 2, 1, 3], optimum(12, 1)),
+        ];
+        let mut graph = OptimaGraph::star(&local_minimums, TourMetric::Bond);
+
+        let mut out = vec![];
+        graph.write(&mut out, GraphFormat::Dot).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+
+        assert!(dot.starts_with("graph local_optima {"));
+        assert!(dot.contains("1 [label=\"12\", tour=\"[0, 2, 1, 3]\", tour_len=12, basin_size=1, distance_to_best=2];"));
+        assert!(dot.contains("0 -- 1 [label=\"2\", \"bond_distance\"=2];"));
+
+        //the attribute name is quoted, so spaces and quotes stay one id
+        graph.edge_attribute = String::from("bond \"distance\"");
+        let mut out = vec![];
+        graph.write(&mut out, GraphFormat::Dot).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains(r#"0 -- 1 [label="2", "bond \"distance\""=2];"#));
+    }
+
+    /// Tags open and close in order, `<?xml` declarations and self-closing tags aside
+    fn assert_balanced_xml(xml: &str) {
+        let mut open: Vec<&str> = vec![];
+        for tag in xml.split('<').skip(1) {
+            let tag = &tag[..tag.find('>').expect("Unclosed tag")];
+            if tag.starts_with('?') || tag.ends_with('/') {
+                continue;
+            }
+            match tag.strip_prefix('/') {
+                Some(name) => assert_eq!(open.pop(), Some(name), "{}", xml),
+                None => open.push(tag.split_whitespace().next().unwrap()),
+            }
+        }
+        assert!(open.is_empty(), "{:?}", open);
+        assert!(!xml.contains("\"<b>"), "unescaped attribute");
+    }
+
+    /// Brackets outside of strings are balanced and every string is terminated
+    fn assert_balanced_json(json: &str) {
+        let mut open = vec![];
+        let (mut in_string, mut escaped) = (false, false);
+        for c in json.chars() {
+            match (in_string, escaped, c) {
+                (true, true, _) => escaped = false,
+                (true, false, '\\') => escaped = true,
+                (true, false, '"') => in_string = false,
+                (true, false, _) => {}
+                (false, _, '"') => in_string = true,
+                (false, _, '{' | '[') => open.push(c),
+                (false, _, '}') => assert_eq!(open.pop(), Some('{')),
+                (false, _, ']') => assert_eq!(open.pop(), Some('[')),
+                _ => {}
+            }
+        }
+        assert!(open.is_empty() && !in_string, "{}", json);
+    }
+
+    fn written(graph: &OptimaGraph, format: GraphFormat) -> String {
+        let mut out = vec![];
+        graph.write(&mut out, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn xml_and_json_formats() {
+        let local_minimums = vec![
+            (vec![0, 1, 2, 3], optimum(10, 5)),
+            (vec![0, 2, 1, 3], optimum(12, 1)),
+        ];
+        let mut star = OptimaGraph::star(&local_minimums, TourMetric::Bond);
+        star.edge_attribute = String::from("a\"<b>&\\c");
+
+        let mut edges = FxHashMap::default();
+        edges.insert((0, 1), 3);
+        edges.insert((1, 2), 1);
+        let lon = Lon {
+            nodes: vec![
+                (vec![0, 1, 2, 3], 10),
+                (vec![0, 2, 1, 3], 12),
+                (vec![0, 1, 3, 2], 9),
+            ],
+            edges,
+            sampled: 2,
+        };
+        let network = OptimaGraph::from_lon(&lon, &local_minimums, TourMetric::Bond);
+        assert!(network.directed && !star.directed);
+        assert_eq!(network.nodes[2].basin_size, 0);
+
+        let graphml = written(&star, GraphFormat::GraphMl);
+        assert_balanced_xml(&graphml);
+        assert!(graphml.contains(r#"attr.name="a&quot;&lt;b&gt;&amp;\c""#));
+        assert!(graphml.contains(r#"edgedefault="undirected""#));
+        assert!(
+            graphml.contains(r#"<edge source="n0" target="n1"><data key="weight">2</data></edge>"#)
+        );
+        let graphml = written(&network, GraphFormat::GraphMl);
+        assert_balanced_xml(&graphml);
+        assert!(graphml.contains(r#"edgedefault="directed""#));
+        assert!(
+            graphml.contains(r#"<edge source="n1" target="n2"><data key="weight">1</data></edge>"#)
+        );
+
+        let gexf = written(&star, GraphFormat::Gexf);
+        assert_balanced_xml(&gexf);
+        assert!(gexf.contains(r#"title="a&quot;&lt;b&gt;&amp;\c""#));
+        assert!(gexf.contains(r#"defaultedgetype="undirected""#));
+        let gexf = written(&network, GraphFormat::Gexf);
+        assert_balanced_xml(&gexf);
+        assert!(gexf.contains(r#"defaultedgetype="directed""#));
+        assert!(gexf.contains(r#"<edge id="0" source="0" target="1" weight="3">"#));
+        assert_eq!(gexf.matches("<node id=").count(), 3);
+
+        let json = written(&star, GraphFormat::Json);
+        assert_balanced_json(&json);
+        assert!(json.contains(r#""directed": false"#));
+        assert!(json.contains(r#"{"source": 0, "target": 1, "a\"<b>&\\c": 2}"#));
+        let json = written(&network, GraphFormat::Json);
+        assert_balanced_json(&json);
+        assert!(json.contains(r#""directed": true"#));
+        assert!(json.contains(r#"{"source": 1, "target": 2, "transitions": 1}"#));
+        assert!(json.contains(
+            r#"{"id": 2, "tour": [0, 1, 3, 2], "tour_len": 9, "basin_size": 0, "distance_to_best": 0}"#
+        ));
+    }
+}
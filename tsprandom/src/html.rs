@@ -0,0 +1,199 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::export::{GraphFormat, OptimaGraph};
+
+/// Number of best optima drawn by default
+pub const DEFAULT_TOP_N: usize = 40;
+
+const TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>__TITLE__</title>
+<style>
+  body { margin: 0; font-family: sans-serif; }
+  #controls { padding: 8px; background: #f4f4f4; border-bottom: 1px solid #ccc; }
+  #tooltip { position: absolute; display: none; max-width: 420px; padding: 6px; background: #fff;
+             border: 1px solid #888; font-size: 12px; pointer-events: none; word-break: break-all; }
+  canvas { display: block; }
+</style>
+</head>
+<body>
+<div id="controls">
+  <b>__TITLE__</b> &mdash; max tour length:
+  <input id="filter" type="range">
+  <span id="filter-value"></span>
+  (<span id="shown"></span> of <span id="total"></span> optima shown, edges labelled with __EDGE__)
+</div>
+<div id="tooltip"></div>
+<canvas id="graph"></canvas>
+<script>
+const data = __DATA__;
+const canvas = document.getElementById("graph");
+const ctx = canvas.getContext("2d");
+const tooltip = document.getElementById("tooltip");
+const filter = document.getElementById("filter");
+
+const lens = data.nodes.map(n => n.tour_len);
+const minLen = Math.min(...lens), maxLen = Math.max(...lens);
+const maxBasin = Math.max(1, ...data.nodes.map(n => n.basin_size));
+const maxDist = Math.max(1, ...data.nodes.map(n => n.distance_to_best));
+filter.min = minLen; filter.max = maxLen; filter.value = maxLen;
+document.getElementById("total").textContent = data.nodes.length;
+
+function hsvToRgb(h, s, v) {
+  const i = Math.floor(h * 6), f = h * 6 - i;
+  const p = v * (1 - s), q = v * (1 - f * s), t = v * (1 - (1 - f) * s);
+  const [r, g, b] = [[v, t, p], [q, v, p], [p, v, t], [p, q, v], [t, p, v], [v, p, q]][i % 6];
+  return `rgb(${Math.round(r * 255)},${Math.round(g * 255)},${Math.round(b * 255)})`;
+}
+
+//green for the best optimum, red for the worst shown
+function color(node) {
+  const s = maxLen > minLen ? (node.tour_len - minLen) / (maxLen - minLen) : 0;
+  return hsvToRgb((1 - s) * 0.3, 1, 1);
+}
+
+function layout() {
+  canvas.width = window.innerWidth;
+  canvas.height = window.innerHeight - document.getElementById("controls").offsetHeight;
+  const cx = canvas.width / 2, cy = canvas.height / 2;
+  const radius = Math.min(cx, cy) - 40;
+  //optima are placed around the best one, further away the more distant they are
+  data.nodes.forEach((node, i) => {
+    const angle = 2 * Math.PI * i / Math.max(1, data.nodes.length - 1);
+    const r = i === 0 ? 0 : radius * (0.2 + 0.8 * node.distance_to_best / maxDist);
+    node.x = cx + r * Math.cos(angle);
+    node.y = cy + r * Math.sin(angle);
+    node.size = 4 + 21 * node.basin_size / maxBasin;
+  });
+}
+
+function visible(node) {
+  return node.tour_len <= Number(filter.value);
+}
+
+function draw() {
+  ctx.clearRect(0, 0, canvas.width, canvas.height);
+  ctx.font = "11px sans-serif";
+  ctx.textAlign = "center";
+  for (const edge of data.links) {
+    const a = data.nodes[edge.source], b = data.nodes[edge.target];
+    if (!visible(a) || !visible(b)) continue;
+    ctx.strokeStyle = "#888888";
+    ctx.beginPath(); ctx.moveTo(a.x, a.y); ctx.lineTo(b.x, b.y); ctx.stroke();
+    ctx.fillStyle = "#555555";
+    ctx.fillText(edge[data.graph.edge_attribute], (a.x + b.x) / 2, (a.y + b.y) / 2 - 3);
+  }
+  let shown = 0;
+  data.nodes.forEach((node, i) => {
+    if (!visible(node)) return;
+    shown++;
+    ctx.fillStyle = color(node);
+    ctx.strokeStyle = "#333333";
+    ctx.beginPath(); ctx.arc(node.x, node.y, node.size, 0, 2 * Math.PI); ctx.fill(); ctx.stroke();
+    ctx.fillStyle = "#000000";
+    const label = (node.tour_len === minLen ? "*" : "") + node.tour_len;
+    ctx.fillText(label, node.x, node.y - node.size - 4);
+  });
+  document.getElementById("shown").textContent = shown;
+  document.getElementById("filter-value").textContent = filter.value;
+}
+
+canvas.addEventListener("mousemove", e => {
+  const rect = canvas.getBoundingClientRect();
+  const x = e.clientX - rect.left, y = e.clientY - rect.top;
+  const node = data.nodes.find(n => visible(n) && Math.hypot(n.x - x, n.y - y) <= Math.max(n.size, 5));
+  if (!node) { tooltip.style.display = "none"; return; }
+  tooltip.innerHTML = `Tour: [${node.tour.join(", ")}]<br>Tour len: ${node.tour_len}<br>` +
+    `${node.basin_size} starting points led to this LO<br>Distance to best: ${node.distance_to_best}`;
+  tooltip.style.left = (e.pageX + 12) + "px";
+  tooltip.style.top = (e.pageY + 12) + "px";
+  tooltip.style.display = "block";
+});
+filter.addEventListener("input", draw);
+window.addEventListener("resize", () => { layout(); draw(); });
+layout();
+draw();
+</script>
+</body>
+</html>
+"##;
+
+/// Writes a page drawing the first `top_n` nodes of the graph without any external resources,
+/// the data is the node-link JSON of the export
+pub fn write_html<W: Write>(
+    graph: &OptimaGraph,
+    top_n: usize,
+    title: &str,
+    w: &mut W,
+) -> io::Result<()> {
+    let mut data = vec![];
+    graph.top(top_n).write(&mut data, GraphFormat::Json)?;
+    //a string in the data must not end the script element
+    let data = String::from_utf8_lossy(&data).replace("</", "<\\/");
+
+    let page = TEMPLATE
+        .replace("__TITLE__", &html_escape(title))
+        .replace(
+            "__EDGE__",
+            &html_escape(&graph.edge_attribute.replace('_', " ")),
+        )
+        .replace("__DATA__", &data);
+    w.write_all(page.as_bytes())
+}
+
+pub fn save_html(graph: &OptimaGraph, top_n: usize, title: &str, path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_html(graph, top_n, title, &mut writer)?;
+    writer.flush()
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{GraphEdge, GraphNode};
+
+    #[test]
+    fn page_embeds_the_top_nodes() {
+        let node = |tour_len| GraphNode {
+            tour: vec![0, 1, 2],
+            tour_len,
+            basin_size: 1,
+            distance_to_best: 0,
+        };
+        let edge = |source, target| GraphEdge {
+            source,
+            target,
+            weight: 7,
+        };
+        let graph = OptimaGraph {
+            nodes: vec![node(10), node(11), node(12)],
+            edges: vec![edge(0, 1), edge(1, 2), edge(2, 0)],
+            directed: true,
+            edge_attribute: String::from("escape_count"),
+        };
+
+        let mut out = vec![];
+        write_html(&graph, 2, "g10 <2opt> & more", &mut out).unwrap();
+        let page = String::from_utf8(out).unwrap();
+
+        assert!(!page.contains("__TITLE__") && !page.contains("__EDGE__"));
+        assert!(!page.contains("__DATA__"));
+        assert!(page.contains("<title>g10 &lt;2opt&gt; &amp; more</title>"));
+        assert!(page.contains("edges labelled with escape count)"));
+        assert!(page.contains(r#""tour_len": 11"#) && !page.contains(r#""tour_len": 12"#));
+        assert!(page.contains(r#"{"source": 0, "target": 1, "escape_count": 7}"#));
+        assert!(!page.contains(r#""target": 2"#) && !page.contains(r#""source": 2"#));
+    }
+}
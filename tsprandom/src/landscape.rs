@@ -0,0 +1,427 @@
+//! Ruggedness of the landscape seen by the local searches, measured on random walks through
+//! their neighbourhoods: autocorrelation (Weinberger) and information content (Vassilev et al.),
+//! and the descents of the local searches to their optima.
+
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use tsptools::{
+    algorithms::{
+        hillclimb::{hillclimb_traced, swap_delta},
+        two_opt::{two_opt_delta, two_opt_traced},
+    },
+    helpers::{random_solution, tour_len},
+    trace::Move,
+};
+
+/// Moves of a local search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// Swap of two cities, as in `hillclimb`
+    Swap,
+    /// Reversal of a segment, as in `two_opt`
+    TwoOpt,
+}
+
+impl Neighbourhood {
+    pub const ALL: [Neighbourhood; 2] = [Neighbourhood::Swap, Neighbourhood::TwoOpt];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Swap => "swap",
+            Self::TwoOpt => "2opt",
+        }
+    }
+}
+
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "swap" => Ok(Self::Swap),
+            "2opt" => Ok(Self::TwoOpt),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+impl Display for Neighbourhood {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Tour lengths along a walk of `steps` random moves from a random tour, updated by the move
+/// deltas. Returns the lengths, `steps + 1` of them, and the last tour.
+pub fn random_walk<R: Rng>(
+    distance_matrix: &[Vec<i32>],
+    neighbourhood: Neighbourhood,
+    steps: usize,
+    preserve_first: bool,
+    symmetric: bool,
+    rng: &mut R,
+) -> (Vec<i32>, Vec<u16>) {
+    let n = distance_matrix.len();
+    let mut tour = random_solution(n as u16, Some(rng.gen()), preserve_first);
+    let mut len = tour_len(&tour, distance_matrix);
+    let start = match preserve_first {
+        true => 1,
+        false => 0,
+    };
+
+    let mut lengths = Vec::with_capacity(steps + 1);
+    lengths.push(len);
+    for _ in 0..steps {
+        let a = rng.gen_range(start..n);
+        let b = rng.gen_range(start..n - 1);
+        let (i, j) = match b >= a {
+            true => (a, b + 1),
+            false => (b, a),
+        };
+        match neighbourhood {
+            Neighbourhood::Swap => {
+                len += swap_delta(&tour, i, j, distance_matrix);
+                tour.swap(i, j);
+            }
+            //the reversed segment changes direction, which only the full length sees on
+            //asymmetric instances
+            Neighbourhood::TwoOpt if symmetric => {
+                len += two_opt_delta(&tour, i, j, distance_matrix);
+                tour[i + 1..=j].reverse();
+            }
+            Neighbourhood::TwoOpt => {
+                tour[i + 1..=j].reverse();
+                len = tour_len(&tour, distance_matrix);
+            }
+        }
+        lengths.push(len);
+    }
+    (lengths, tour)
+}
+
+/// Autocorrelation of the series for the lags `0..=max_lag`, None for a constant series
+pub fn autocorrelation(series: &[f64], max_lag: usize) -> Option<Vec<f64>> {
+    let n = series.len();
+    let mean = series.iter().sum::<f64>() / n as f64;
+    let variance: f64 = series.iter().map(|x| (x - mean).powi(2)).sum();
+    if n == 0 || variance <= 0.0 {
+        return None;
+    }
+    let acf = (0..=max_lag.min(n - 1))
+        .map(|lag| {
+            let covariance: f64 = series
+                .iter()
+                .zip(&series[lag..])
+                .map(|(x, y)| (x - mean) * (y - mean))
+                .sum();
+            covariance / variance
+        })
+        .collect();
+    Some(acf)
+}
+
+/// Steps after which the correlation drops to 1/e, `-1 / ln(rho(1))`
+pub fn correlation_length(rho1: f64) -> Option<f64> {
+    (rho1 > 0.0 && rho1 < 1.0).then(|| -1.0 / rho1.ln())
+}
+
+/// Direction of every step: -1, 0 or 1, where changes up to `epsilon` count as flat
+fn symbols(series: &[f64], epsilon: f64) -> Vec<i8> {
+    series
+        .windows(2)
+        .map(|w| match w[1] - w[0] {
+            d if d < -epsilon => -1,
+            d if d > epsilon => 1,
+            _ => 0,
+        })
+        .collect()
+}
+
+/// Entropy of the pairs of consecutive steps with different directions, in base 6 so that it
+/// lies in [0, 1]
+pub fn information_content(series: &[f64], epsilon: f64) -> f64 {
+    let symbols = symbols(series, epsilon);
+    if symbols.len() < 2 {
+        return 0.0;
+    }
+    let mut counts = [0usize; 9];
+    for pair in symbols.windows(2).filter(|pair| pair[0] != pair[1]) {
+        counts[((pair[0] + 1) * 3 + pair[1] + 1) as usize] += 1;
+    }
+    let pairs = (symbols.len() - 1) as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / pairs;
+            -p * p.log(6.0)
+        })
+        .sum()
+}
+
+/// Length of the steps with the flat ones and repeated directions removed, relative to the
+/// number of steps. Half of it times the steps estimates the optima along the walk.
+pub fn partial_information_content(series: &[f64], epsilon: f64) -> f64 {
+    let symbols = symbols(series, epsilon);
+    if symbols.is_empty() {
+        return 0.0;
+    }
+    let mut last = 0;
+    let mut alternations = 0;
+    for &symbol in &symbols {
+        if symbol != 0 && symbol != last {
+            alternations += 1;
+            last = symbol;
+        }
+    }
+    alternations as f64 / symbols.len() as f64
+}
+
+/// Measures of one walk
+pub struct WalkStats {
+    pub mean_len: f64,
+    pub std_dev_len: f64,
+    pub acf: Vec<f64>,
+    pub correlation_length: Option<f64>,
+    pub information_content: f64,
+    pub partial_information_content: f64,
+    /// Largest change of a single step, from which on the information content is 0
+    pub information_stability: f64,
+    /// Information content and partial information content at `k / epsilon_steps` times the
+    /// information stability for `k` in `0..=epsilon_steps`, comparable between walks
+    pub information_curve: Vec<(f64, f64)>,
+}
+
+impl WalkStats {
+    pub fn new(lengths: &[i32], max_lag: usize, epsilon: f64, epsilon_steps: usize) -> Self {
+        let series: Vec<f64> = lengths.iter().map(|&len| len as f64).collect();
+        let n = series.len() as f64;
+        let mean_len = series.iter().sum::<f64>() / n;
+        let variance = series.iter().map(|x| (x - mean_len).powi(2)).sum::<f64>() / n;
+        let acf = autocorrelation(&series, max_lag).unwrap_or_default();
+        let information_stability = series
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0.0, f64::max);
+
+        WalkStats {
+            mean_len,
+            std_dev_len: variance.sqrt(),
+            correlation_length: acf.get(1).and_then(|&rho1| correlation_length(rho1)),
+            acf,
+            information_content: information_content(&series, epsilon),
+            partial_information_content: partial_information_content(&series, epsilon),
+            information_stability,
+            information_curve: (0..=epsilon_steps)
+                .map(|k| {
+                    let epsilon = information_stability * k as f64 / epsilon_steps.max(1) as f64;
+                    (
+                        information_content(&series, epsilon),
+                        partial_information_content(&series, epsilon),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Runs the walks in parallel. Walk `i` draws from stream `i` of the seed, so the walks do not
+/// depend on the number of threads.
+#[allow(clippy::too_many_arguments)]
+pub fn walks(
+    distance_matrix: &[Vec<i32>],
+    neighbourhood: Neighbourhood,
+    count: usize,
+    steps: usize,
+    preserve_first: bool,
+    threads: usize,
+    seed: u64,
+    measure: impl Fn(&[i32]) -> WalkStats + Sync,
+) -> Vec<WalkStats> {
+    let symmetric = tsptools::helpers::is_symmetric(distance_matrix);
+    in_parallel(count, threads, |i| {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(i as u64);
+        let (lengths, _) = random_walk(
+            distance_matrix,
+            neighbourhood,
+            steps,
+            preserve_first,
+            symmetric,
+            &mut rng,
+        );
+        measure(&lengths)
+    })
+}
+
+/// Local search from a random tour with the moves it accepted
+pub struct Descent {
+    pub starting_len: i32,
+    pub moves: Vec<Move>,
+}
+
+impl Descent {
+    pub fn optimum_len(&self) -> i32 {
+        self.moves.last().map_or(self.starting_len, |step| step.len)
+    }
+}
+
+/// Runs the local search of the neighbourhood from random tours in parallel, tracing the
+/// accepted moves. Descent `i` starts from a tour of stream `i` in the upper half of the streams,
+/// apart from the ones of the walks.
+pub fn descents(
+    distance_matrix: &[Vec<i32>],
+    neighbourhood: Neighbourhood,
+    count: usize,
+    preserve_first: bool,
+    threads: usize,
+    seed: u64,
+) -> Vec<Descent> {
+    in_parallel(count, threads, |i| {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(1 << 63 | i as u64);
+        let start = random_solution(
+            distance_matrix.len() as u16,
+            Some(rng.gen()),
+            preserve_first,
+        );
+        let mut moves = vec![];
+        match neighbourhood {
+            Neighbourhood::Swap => {
+                hillclimb_traced(&start, distance_matrix, preserve_first, &mut moves)
+            }
+            Neighbourhood::TwoOpt => {
+                two_opt_traced(&start, distance_matrix, preserve_first, &mut moves)
+            }
+        };
+        Descent {
+            starting_len: tour_len(&start, distance_matrix),
+            moves,
+        }
+    })
+}
+
+/// Computes `run(i)` for `i` in `0..count` on the threads, in order of `i`
+fn in_parallel<T: Send>(count: usize, threads: usize, run: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, T)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut found = vec![];
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= count {
+                            break;
+                        }
+                        found.push((i, run(i)));
+                    }
+                    found
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("Landscape thread panicked"))
+            .collect()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Autocorrelation at every lag averaged over the walks on which it is defined
+pub fn mean_acf(walks: &[WalkStats]) -> Vec<f64> {
+    let lags = walks.iter().map(|walk| walk.acf.len()).max().unwrap_or(0);
+    (0..lags)
+        .map(|lag| {
+            let values: Vec<f64> = walks
+                .iter()
+                .filter_map(|walk| walk.acf.get(lag))
+                .copied()
+                .collect();
+            values.iter().sum::<f64>() / values.len() as f64
+        })
+        .collect()
+}
+
+/// Information content curves averaged over the walks, keyed by the relative epsilon
+pub fn mean_information_curve(walks: &[WalkStats]) -> Vec<(f64, (f64, f64))> {
+    let Some(first) = walks.first() else {
+        return vec![];
+    };
+    let steps = (first.information_curve.len() - 1).max(1);
+    (0..first.information_curve.len())
+        .map(|k| {
+            let (h, m) = walks.iter().fold((0.0, 0.0), |(h, m), walk| {
+                (
+                    h + walk.information_curve[k].0,
+                    m + walk.information_curve[k].1,
+                )
+            });
+            let count = walks.len() as f64;
+            (k as f64 / steps as f64, (h / count, m / count))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::skewed_matrix;
+
+    #[test]
+    fn walk_lengths_follow_the_deltas() {
+        let distance_matrix = skewed_matrix(8, None);
+        let asymmetric: Vec<Vec<i32>> = (0..8)
+            .map(|i: i32| {
+                (0..8)
+                    .map(|j: i32| (i - j).abs() + 3 * i * (j % 3))
+                    .collect()
+            })
+            .collect();
+
+        for (matrix, symmetric) in [(&distance_matrix, true), (&asymmetric, false)] {
+            for neighbourhood in Neighbourhood::ALL {
+                let mut rng = ChaCha8Rng::seed_from_u64(3);
+                let (lengths, tour) =
+                    random_walk(matrix, neighbourhood, 500, true, symmetric, &mut rng);
+                assert_eq!(lengths.len(), 501);
+                assert_eq!(tour[0], 0);
+                assert_eq!(lengths[500], tour_len(&tour, matrix));
+            }
+        }
+    }
+
+    #[test]
+    fn ruggedness_of_simple_series() {
+        let alternating = [1.0, 3.0, 1.0, 3.0, 1.0, 3.0];
+        let acf = autocorrelation(&alternating, 2).unwrap();
+        assert_eq!(acf[0], 1.0);
+        assert!(acf[1] < -0.8 && acf[2] > 0.6);
+        assert!(autocorrelation(&[2.0, 2.0], 1).is_none());
+        assert!((correlation_length(1.0 / std::f64::consts::E).unwrap() - 1.0).abs() < 1e-12);
+
+        //steps up, down, up, down, up: only the pairs (1, -1) and (-1, 1), each half of the time
+        let expected = -(0.5f64.log(6.0));
+        assert!((information_content(&alternating, 0.0) - expected).abs() < 1e-12);
+        assert_eq!(partial_information_content(&alternating, 0.0), 1.0);
+        assert_eq!(information_content(&alternating, 2.0), 0.0);
+        assert_eq!(partial_information_content(&alternating, 2.0), 0.0);
+
+        let rising = [1.0, 2.0, 3.0, 3.0, 4.0];
+        //(1, 0) and (0, 1) once each among three pairs
+        let p: f64 = 1.0 / 3.0;
+        assert!((information_content(&rising, 0.0) + 2.0 * p * p.log(6.0)).abs() < 1e-12);
+        assert_eq!(partial_information_content(&rising, 0.0), 0.25);
+    }
+}
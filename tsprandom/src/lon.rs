@@ -0,0 +1,311 @@
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use rand::{seq::index::sample, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rustc_hash::FxHashMap;
+use tsptools::solver::{solver_by_name, Budget, CancelToken};
+
+use crate::{canonical_key, LocalOptimum};
+
+/// Kick used to escape from a local optimum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Perturbation {
+    DoubleBridge,
+    RandomSwaps(usize),
+}
+
+impl Perturbation {
+    /// Perturbs the tour, keeping the first city in place
+    pub fn apply<R: Rng>(&self, tour: &[u16], rng: &mut R) -> Vec<u16> {
+        let n = tour.len();
+        match self {
+            Self::DoubleBridge => {
+                if n < 5 {
+                    return tour.to_vec();
+                }
+                //A B C D -> A C B D with A starting at position 0
+                let mut cuts = sample(rng, n - 1, 3).into_vec();
+                cuts.sort_unstable();
+                let (i, j, k) = (cuts[0] + 1, cuts[1] + 1, cuts[2] + 1);
+
+                let mut perturbed = Vec::with_capacity(n);
+                perturbed.extend_from_slice(&tour[..i]);
+                perturbed.extend_from_slice(&tour[j..k]);
+                perturbed.extend_from_slice(&tour[i..j]);
+                perturbed.extend_from_slice(&tour[k..]);
+                perturbed
+            }
+            Self::RandomSwaps(k) => {
+                let mut perturbed = tour.to_vec();
+                if n < 3 {
+                    return perturbed;
+                }
+                for _ in 0..*k {
+                    let a = rng.gen_range(1..n);
+                    let b = rng.gen_range(1..n);
+                    perturbed.swap(a, b);
+                }
+                perturbed
+            }
+        }
+    }
+}
+
+impl FromStr for Perturbation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "double-bridge" => Ok(Self::DoubleBridge),
+            Some(("swaps", k)) => k.parse().map(Self::RandomSwaps).map_err(|_| s.to_owned()),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+impl Display for Perturbation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DoubleBridge => write!(f, "double-bridge"),
+            Self::RandomSwaps(k) => write!(f, "swaps:{}", k),
+        }
+    }
+}
+
+/// Local Optima Network: directed graph of optima weighted by the number of escapes
+/// from the source optimum that ended in the target optimum
+#[derive(Debug, Clone)]
+pub struct Lon {
+    /// Canonical tour and length of every node. The sampled optima come first in their
+    /// original order, optima only found by escapes are appended.
+    pub nodes: Vec<(Vec<u16>, i32)>,
+    pub edges: FxHashMap<(usize, usize), u32>,
+    /// Number of nodes taken from the sample
+    pub sampled: usize,
+}
+
+impl Lon {
+    /// Escapes ending in the same optimum they started from
+    pub fn self_loops(&self) -> u32 {
+        self.edges
+            .iter()
+            .filter(|((source, target), _)| source == target)
+            .map(|(_, weight)| *weight)
+            .sum()
+    }
+
+    /// Sampled optima from which no escape reached a shorter optimum
+    pub fn sinks(&self) -> usize {
+        (0..self.sampled)
+            .filter(|&node| {
+                !self.edges.keys().any(|&(source, target)| {
+                    source == node && self.nodes[target].1 < self.nodes[node].1
+                })
+            })
+            .count()
+    }
+}
+
+impl Display for Lon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let escapes: u32 = self.edges.values().sum();
+        writeln!(
+            f,
+            "LON nodes: {} ({} sampled), edges: {}",
+            self.nodes.len(),
+            self.sampled,
+            self.edges.len()
+        )?;
+        write!(
+            f,
+            "Escapes: {}, ending in the same optimum: {}, sinks: {}",
+            escapes,
+            self.self_loops(),
+            self.sinks()
+        )
+    }
+}
+
+/// Canonical tours and lengths of the optima reached by the escapes from one optimum
+type Escapes = Vec<(Vec<u16>, i32)>;
+
+/// Applies `escapes` perturbations followed by local search to every sampled optimum
+#[allow(clippy::too_many_arguments)]
+pub fn build_lon(
+    local_minimums: &[(Vec<u16>, LocalOptimum)],
+    distance_matrix: &[Vec<i32>],
+    alg: &str,
+    perturbation: Perturbation,
+    escapes: usize,
+    symmetric: bool,
+    threads: usize,
+    seed: u64,
+) -> Lon {
+    let next_optimum = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, Escapes)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut solver = solver_by_name(alg).expect("Invalid algorithm param");
+                    solver
+                        .configure("preserve_first", "true")
+                        .expect("Algorithm does not support preserve_first");
+                    let cancel = CancelToken::new();
+
+                    let mut found = vec![];
+                    loop {
+                        let i = next_optimum.fetch_add(1, Ordering::Relaxed);
+                        if i >= local_minimums.len() {
+                            break;
+                        }
+                        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                        rng.set_stream(i as u64);
+
+                        let tour = &local_minimums[i].0;
+                        let targets = (0..escapes)
+                            .map(|_| {
+                                let start = perturbation.apply(tour, &mut rng);
+                                solver
+                                    .configure("seed", &rng.gen::<u64>().to_string())
+                                    .expect("Algorithm does not support seed");
+                                let solution = solver.solve(
+                                    distance_matrix,
+                                    Some(&start),
+                                    &Budget::unlimited(),
+                                    &cancel,
+                                    &mut |_| {},
+                                );
+                                (canonical_key(&solution.tour, symmetric), solution.len)
+                            })
+                            .collect();
+                        found.push((i, targets));
+                    }
+                    found
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("LON thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+
+    let mut nodes: Vec<(Vec<u16>, i32)> = local_minimums
+        .iter()
+        .map(|(tour, optimum)| (tour.clone(), optimum.len))
+        .collect();
+    let mut ids: FxHashMap<Vec<u16>, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, (tour, _))| (tour.clone(), i))
+        .collect();
+    let mut edges = FxHashMap::default();
+
+    for (source, targets) in results {
+        for (tour, len) in targets {
+            let target = *ids.entry(tour.clone()).or_insert_with(|| {
+                nodes.push((tour, len));
+                nodes.len() - 1
+            });
+            *edges.entry((source, target)).or_insert(0) += 1;
+        }
+    }
+
+    Lon {
+        nodes,
+        edges,
+        sampled: local_minimums.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tsptools::helpers::random_solution;
+
+    use super::*;
+    use crate::{
+        sampler::{local_search, new_solver},
+        test_utils::{is_permutation, skewed_matrix},
+    };
+
+    #[test]
+    fn perturbations_keep_the_first_city() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for perturbation in [
+            Perturbation::DoubleBridge,
+            Perturbation::RandomSwaps(1),
+            Perturbation::RandomSwaps(5),
+        ] {
+            for n in [3, 5, 12] {
+                for _ in 0..50 {
+                    let tour = random_solution(n, Some(rng.gen()), true);
+                    let perturbed = perturbation.apply(&tour, &mut rng);
+                    assert!(is_permutation(&perturbed, n as usize), "{:?}", perturbed);
+                    assert_eq!(perturbed[0], 0);
+                }
+            }
+        }
+        assert_eq!(
+            "swaps:3".parse::<Perturbation>(),
+            Ok(Perturbation::RandomSwaps(3))
+        );
+        assert_eq!(Perturbation::DoubleBridge.to_string(), "double-bridge");
+    }
+
+    #[test]
+    fn lon_counts_every_escape_independently_of_threads() {
+        let distance_matrix = skewed_matrix(9, Some(23));
+        let mut solver = new_solver("2opt", true);
+        let mut optima: FxHashMap<Vec<u16>, LocalOptimum> = FxHashMap::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for _ in 0..30 {
+            let start = random_solution(9, Some(rng.gen()), true);
+            let (tour, len) = local_search(solver.as_mut(), &mut rng, &start, &distance_matrix);
+            optima
+                .entry(canonical_key(&tour, true))
+                .or_insert_with(|| LocalOptimum {
+                    len,
+                    related_starting_points: 0,
+                    variants: FxHashMap::default(),
+                })
+                .related_starting_points += 1;
+        }
+        let mut optima: Vec<_> = optima.into_iter().collect();
+        optima.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let escapes = 7;
+        let build = |threads| {
+            build_lon(
+                &optima,
+                &distance_matrix,
+                "2opt",
+                Perturbation::DoubleBridge,
+                escapes,
+                true,
+                threads,
+                3,
+            )
+        };
+        let single = build(1);
+        let multi = build(4);
+
+        assert_eq!(single.sampled, optima.len());
+        let weights: u32 = single.edges.values().sum();
+        assert_eq!(weights as usize, escapes * optima.len());
+        assert!(single
+            .edges
+            .keys()
+            .all(|&(source, target)| source < single.sampled && target < single.nodes.len()));
+
+        assert_eq!(single.nodes, multi.nodes);
+        assert_eq!(single.edges, multi.edges);
+    }
+}
@@ -1,281 +1,875 @@
 use std::{
-    env,
-    fs::File,
-    io::Write,
-    process::Command,
-    sync::Mutex,
-    thread::{self, available_parallelism},
+    error::Error,
+    path::Path,
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+    thread::available_parallelism,
+    time::{Duration, Instant},
 };
 
-use rustc_hash::{FxHashMap, FxHashSet};
+use checkpoint::{Checkpointer, RunInfo};
+use clap::Parser;
+use rustc_hash::FxHashMap;
 use tsptools::{
-    algorithms::{hillclimb::hillclimb, two_opt::two_opt},
-    helpers::{cmp_permutations, random_solution},
-    parsers::parse_tsp_file,
+    helpers::is_symmetric, parsers::parse_tsp_file, tour::Tour, tour_distance::TourMetric,
+    trace::descent_curve,
 };
 
+use cli::{
+    AnalysisArgs, Cli, Command, ExhaustiveArgs, LonArgs, OutputArgs, PlotArgs, SampleArgs,
+    StatsArgs, WalkArgs,
+};
+use dedup::{Dedup, VisitedSet};
+use estimators::{basin_shares, estimate_optima, Estimate};
+use exhaustive::MAX_CITIES;
+use export::OptimaGraph;
+use html::save_html;
+use landscape::{mean_acf, mean_information_curve, Descent, Neighbourhood, WalkStats};
+use lon::build_lon;
+use output::{
+    load_local_optima, save_basins, save_descents, save_graph, save_lon, save_results, save_walks,
+    Output, StartingPointsWriter,
+};
 use plotters::prelude::*;
-
+use rand::Rng;
+use rand_chacha::{rand_core::SeedableRng, ChaCha8Rng};
+use sampler::{Sampling, Stop};
+use stats::{fitness_distance_correlation, FdcResult};
+
+mod checkpoint;
+mod cli;
+mod dedup;
+mod estimators;
+mod exhaustive;
+mod export;
+mod html;
+mod landscape;
+mod lon;
+mod output;
+mod sampler;
+mod shards;
 mod simpleparser;
+mod stable_hash;
+mod stats;
+#[cfg(test)]
+mod test_utils;
+
+/// Local optimum identified by its edge set, together with the raw tours that represent it
+#[derive(Debug, Clone)]
+struct LocalOptimum {
+    len: i32,
+    related_starting_points: i32,
+    /// Raw tours returned by the local search and how often each was reached
+    variants: FxHashMap<Vec<u16>, i32>,
+}
+
+/// Canonical tours of the local optima, paired with what was recorded about them
+type LocalOptima = Vec<(Vec<u16>, LocalOptimum)>;
 
-type HillclimbFunction = dyn Fn(&Vec<u16>, &Vec<Vec<i32>>, bool) -> (Vec<u16>, i32);
+/// Basins listed in the summary
+const LARGEST_BASINS: usize = 10;
+
+/// Points of the information content curve between epsilon 0 and the information stability
+const EPSILON_STEPS: usize = 50;
+
+/// Set by the first Ctrl-C, sampling stops and the results so far are saved
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
 fn main() {
-    let path = env::args().nth(1).expect("No path to input data given!");
-    if path == "--help" || path == "-h" || path == "help" {
-        println!("Usage: tsprandom <path to tsp file> <algorithm> [sample_count (default 10000)] [max_retries (default 10000)]");
-        println!("Supported algorithms: hc, 2opt");
-        return;
+    let result = match Cli::parse().command {
+        Command::Sample(args) => run_sample(&args).map(|_| ()),
+        Command::Lon(args) => run_lon(&args),
+        Command::Exhaustive(args) => run_exhaustive(&args),
+        Command::Walk(args) => run_walk(&args),
+        Command::Stats(args) => run_stats(&args),
+        Command::Plot(args) => run_plot(&args),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
     }
+}
 
-    let (alg, sample_count, max_retries) = get_args();
+/// State of a finished sampling run needed by the follow-up analyses
+struct Sampled {
+    distance_matrix: Vec<Vec<i32>>,
+    local_minimums: LocalOptima,
+    symmetric: bool,
+    threads: usize,
+    lon_seed: u64,
+    output: Output,
+    interrupted: bool,
+}
 
-    let distance_matrix = if path.ends_with(".txt") {
-        simpleparser::parse_simple(&path).expect("Could not parse input file")
-    } else {
-        let file = parse_tsp_file(&path).unwrap();
-        file.distance_matrix
+fn run_sample(args: &SampleArgs) -> Result<Sampled, Box<dyn Error>> {
+    let distance_matrix = read_instance(&args.path)?;
+    if distance_matrix.len() < 3 {
+        return Err(format!("{} has less than 3 cities", args.path).into());
+    }
+
+    let threads = worker_threads(args.threads)?;
+    println!("Using {} threads", threads);
+
+    let fingerprint = checkpoint::fingerprint(&distance_matrix);
+    let resumed = match &args.resume {
+        Some(path) => {
+            let (run, sampling) = checkpoint::load(path)?;
+            run.check(args, fingerprint)?;
+            println!(
+                "Resuming {} after {} samples",
+                path.display(),
+                sampling.samples
+            );
+            Some((run, sampling))
+        }
+        None => None,
+    };
+    let seed = match &resumed {
+        Some((run, _)) => run.seed,
+        None => args
+            .seed
+            .unwrap_or_else(|| ChaCha8Rng::from_entropy().gen()),
     };
+    println!("Seed: {}", seed);
+
+    let symmetric = is_symmetric(&distance_matrix);
+
+    //independent seeds for every random step, all derived from the master seed
+    let mut seeds = ChaCha8Rng::seed_from_u64(seed);
+    let (sample_seed, bootstrap_seed, lon_seed) = (seeds.gen(), seeds.gen(), seeds.gen());
 
-    let algorithm = match alg.as_str() {
-        "hc" => hillclimb,
-        "2opt" => two_opt,
-        _ => panic!("Invalid algorithm param"),
+    let target = match (args.samples, args.time_limit) {
+        (Some(samples), _) => samples,
+        (None, Some(_)) => usize::MAX,
+        (None, None) => 10000,
     };
+    if target == usize::MAX && args.dedup == Dedup::Bloom {
+        return Err("--dedup bloom needs --samples to size the filter".into());
+    }
 
-    let thread_count: usize = available_parallelism().unwrap().get();
-    println!("{} threads available", thread_count);
-
-    let samples_per_thread = sample_count / thread_count;
-
-    let local_minimums = Mutex::new(FxHashMap::<Vec<u16>, (i32, i32)>::default());
-    let visited_starting = Mutex::new(FxHashSet::default());
-
-    thread::scope(|s| {
-        for _ in 0..thread_count {
-            s.spawn(|| {
-                sample(
-                    samples_per_thread,
-                    max_retries,
-                    &distance_matrix,
-                    &local_minimums,
-                    &visited_starting,
-                    &algorithm,
-                );
-            });
+    let (output, mut starting_points, mut sampling) = match resumed {
+        Some((run, sampling)) => {
+            let output = Output::resume(&run.out_dir, &args.alg, &run.timestamp)?;
+            let starting_points = StartingPointsWriter::resume(
+                &output,
+                run.starting_points,
+                run.starting_points_len,
+            )?;
+            (output, starting_points, sampling)
+        }
+        None => {
+            let output = Output::new(&args.output.out_dir, &args.alg)?;
+            let starting_points = StartingPointsWriter::create(&output, args.starting_points)?;
+            let sampling = Sampling::new(args.dedup, target, args.bloom_fp);
+            (output, starting_points, sampling)
         }
-    });
+    };
+    let mut checkpointer = Checkpointer::new(
+        output.path("checkpoint", "ckpt"),
+        RunInfo::new(args, fingerprint, seed, &output),
+        Duration::try_from_secs_f64(args.checkpoint_every).unwrap_or(Duration::MAX),
+    );
 
-    let local_minimums = local_minimums.into_inner().unwrap();
-    let visited_starting = visited_starting.into_inner().unwrap();
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            process::exit(130);
+        }
+        eprintln!("Interrupted, saving the results so far. Press Ctrl-C again to quit at once.");
+    })
+    .map_err(|e| format!("Could not install the Ctrl-C handler: {}", e))?;
+
+    let started = Instant::now();
+    let stop = Stop {
+        deadline: args
+            .time_limit
+            .and_then(|seconds| started.checked_add(Duration::try_from_secs_f64(seconds).ok()?)),
+        interrupted: &INTERRUPTED,
+    };
+    let previous_samples = sampling.samples;
+    sampler::sample(
+        &mut sampling,
+        args,
+        &distance_matrix,
+        symmetric,
+        threads,
+        sample_seed,
+        target,
+        &stop,
+        |sampling, starts| {
+            if let Some(writer) = starting_points.as_mut() {
+                for start in starts {
+                    writer.write_tour(start)?;
+                }
+            }
+            checkpointer.save_if_due(sampling, starting_points.as_mut())
+        },
+    )?;
+    let elapsed = started.elapsed().as_secs_f64();
+    let new_samples = sampling.samples - previous_samples;
+    println!(
+        "Sampled {} starting points in {:.2}s ({:.0} samples/s)",
+        new_samples,
+        elapsed,
+        new_samples as f64 / elapsed
+    );
+    if previous_samples > 0 {
+        println!("{} samples in the whole run", sampling.samples);
+    }
+    if sampling.lost > 0 {
+        println!(
+            "{} samples lost, they found no unused starting point within {} retries",
+            sampling.lost, args.max_retries
+        );
+    }
+    if sampling.exhausted {
+        println!("Stopped early, no sample of the last block found an unused starting point");
+    }
+    if sampling.timed_out {
+        println!("Stopped at the time limit");
+    }
+    if sampling.interrupted {
+        println!("Stopped on Ctrl-C");
+    }
+    if sampling.samples < target && target != usize::MAX {
+        println!(
+            "Only {} of the {} requested samples reached a local optimum",
+            sampling.samples, target
+        );
+    }
+    checkpointer.save(&sampling, starting_points.as_mut())?;
+    println!("Checkpoint saved to {}", checkpointer.path().display());
+    if let Some(starting_points) = starting_points {
+        starting_points.finish()?;
+    }
+    let visited: usize = sampling.visited_starting.iter().map(|v| v.len()).sum();
+    let memory: usize = sampling
+        .visited_starting
+        .iter()
+        .map(|v| v.memory_bytes())
+        .sum();
+    println!(
+        "Visited starting points ({}): {}, ~{:.1} MiB",
+        args.dedup,
+        visited,
+        memory as f64 / (1024.0 * 1024.0)
+    );
+    //the shards fill evenly, so one of them is representative
+    if let Some(VisitedSet::Bloom(filter)) = sampling.visited_starting.iter().next() {
+        println!(
+            "Estimated Bloom filter false positive rate: {:.2e}",
+            filter.false_positive_rate()
+        );
+    }
 
-    let mut local_minimums: Vec<_> = local_minimums
-        .into_iter()
-        .map(|(k, v)| (k, v.0, v.1))
-        .collect();
-    local_minimums.sort_by(|a, b| a.1.cmp(&b.1));
+    let local_minimums = sorted_optima(sampling.local_minimums.into_inner().into_iter().flatten());
+    println!("Found {} local optima", local_minimums.len());
+    print_estimates(&local_minimums);
 
     println!("Calculating stats");
-    calculcate_stats(&local_minimums, &alg);
+    let fdc = calculate_stats(&local_minimums, &args.analysis, Some(bootstrap_seed));
+    println!("{}", fdc);
 
     println!("Saving results...");
-    save_results(&local_minimums, &visited_starting, &alg);
+    save_results(&local_minimums, &output)?;
+    save_basins(&local_minimums, &output)?;
+    save_plots(
+        &local_minimums,
+        &args.alg,
+        &args.analysis,
+        &args.output,
+        &output,
+        !args.no_plot,
+    )?;
+
+    Ok(Sampled {
+        distance_matrix,
+        local_minimums,
+        symmetric,
+        threads,
+        lon_seed,
+        output,
+        interrupted: sampling.interrupted,
+    })
 }
 
-fn get_args() -> (String, usize, usize) {
-    let alg = env::args()
-        .nth(2)
-        .expect("Algorithm param required (hc or 2opt)");
-    let sample_count: usize = match env::args().nth(3) {
-        Some(n) => n.parse().expect("Invalid sample count argument"),
-        None => 10000,
-    };
-
-    let max_retries: usize = match env::args().nth(4) {
-        Some(n) => n.parse().expect("Invalid max_retries argument"),
-        None => 10000,
-    };
+fn run_lon(args: &LonArgs) -> Result<(), Box<dyn Error>> {
+    let sampled = run_sample(&args.sample)?;
+    if sampled.interrupted {
+        println!("Skipping the local optima network of the interrupted run");
+        return Ok(());
+    }
 
-    (alg, sample_count, max_retries)
+    println!("Building local optima network ({})...", args.perturbation);
+    let lon = build_lon(
+        &sampled.local_minimums,
+        &sampled.distance_matrix,
+        &args.sample.alg,
+        args.perturbation,
+        args.escapes,
+        sampled.symmetric,
+        sampled.threads,
+        sampled.lon_seed,
+    );
+    println!("{}", lon);
+    save_lon(
+        &lon,
+        &sampled.local_minimums,
+        args.sample.analysis.distance,
+        &sampled.output,
+        &args.sample.output.formats,
+    )
 }
 
-fn sample(
-    sample_count: usize,
-    max_retries: usize,
-    distance_matrix: &Vec<Vec<i32>>,
-    local_minimums: &Mutex<FxHashMap<Vec<u16>, (i32, i32)>>,
-    visited_starting: &Mutex<FxHashSet<Vec<u16>>>,
-    hc: &HillclimbFunction,
-) {
-    for _ in 0..sample_count {
-        let starting_solution = find_starting_point(visited_starting, distance_matrix, max_retries);
-        if starting_solution.is_none() {
-            return;
-        }
-        let starting_solution = starting_solution.unwrap();
+fn run_exhaustive(args: &ExhaustiveArgs) -> Result<(), Box<dyn Error>> {
+    let distance_matrix = read_instance(&args.path)?;
+    let n = distance_matrix.len();
+    if !(3..=MAX_CITIES).contains(&n) {
+        return Err(format!(
+            "{} has {} cities, exhaustive enumeration needs 3 to {}",
+            args.path, n, MAX_CITIES
+        )
+        .into());
+    }
+    let symmetric = is_symmetric(&distance_matrix);
+    if args.modulo_reversal && !symmetric {
+        return Err("--modulo-reversal needs a symmetric instance".into());
+    }
+
+    let threads = worker_threads(args.threads)?;
+    println!("Using {} threads", threads);
+    let seed = args
+        .seed
+        .unwrap_or_else(|| ChaCha8Rng::from_entropy().gen());
+    println!("Seed: {}", seed);
+    let mut seeds = ChaCha8Rng::seed_from_u64(seed);
+    let (search_seed, bootstrap_seed) = (seeds.gen(), seeds.gen());
+
+    let started = Instant::now();
+    let enumeration = exhaustive::enumerate(
+        &distance_matrix,
+        &args.alg,
+        symmetric,
+        args.modulo_reversal,
+        threads,
+        search_seed,
+    );
+    println!(
+        "Ran the local search from all {} tours in {:.2}s",
+        enumeration.tours,
+        started.elapsed().as_secs_f64()
+    );
 
-        let (hillclimb_tour, hillclimb_len) = (hc)(&starting_solution, distance_matrix, true);
+    let local_minimums = sorted_optima(enumeration.local_minimums);
+    let (global_tour, global_len) = &enumeration.global_optimum;
+    let tours = enumeration.tours as f64;
+    println!("Local optima: {}", local_minimums.len());
+    println!("Global optimum: {} {:?}", global_len, global_tour);
+    let global_basins: i32 = local_minimums
+        .iter()
+        .filter(|(_, optimum)| optimum.len == *global_len)
+        .map(|(_, optimum)| optimum.related_starting_points)
+        .sum();
+    println!(
+        "Tours leading to a global optimum: {} ({:.2}%)",
+        global_basins,
+        100.0 * global_basins as f64 / tours
+    );
+    let mut by_basin: Vec<_> = local_minimums.iter().collect();
+    by_basin.sort_by_key(|(_, optimum)| std::cmp::Reverse(optimum.related_starting_points));
+    println!("Largest basins (tour length, tours):");
+    for (_, optimum) in by_basin.iter().take(LARGEST_BASINS) {
+        println!(
+            "  {} {} ({:.2}%)",
+            optimum.len,
+            optimum.related_starting_points,
+            100.0 * optimum.related_starting_points as f64 / tours
+        );
+    }
 
-        let mut map = local_minimums.lock().expect("Mutex poisoned, bailing out!");
+    println!("Calculating stats");
+    println!(
+        "{}",
+        calculate_stats(&local_minimums, &args.analysis, Some(bootstrap_seed))
+    );
 
-        match map.get_mut(&hillclimb_tour) {
-            Some(v) => {
-                v.1 += 1;
-            }
-            None => {
-                map.insert(hillclimb_tour, (hillclimb_len, 1));
+    println!("Saving results...");
+    let name = format!("{}_exhaustive", args.alg);
+    let output = Output::new(&args.output.out_dir, &name)?;
+    save_results(&local_minimums, &output)?;
+    save_plots(
+        &local_minimums,
+        &name,
+        &args.analysis,
+        &args.output,
+        &output,
+        !args.no_plot,
+    )
+}
+
+fn run_walk(args: &WalkArgs) -> Result<(), Box<dyn Error>> {
+    let distance_matrix = read_instance(&args.path)?;
+    if distance_matrix.len() < 4 {
+        return Err(format!("{} has less than 4 cities", args.path).into());
+    }
+    if args.walks == 0 || args.steps == 0 {
+        return Err("--walks and --steps must be at least 1".into());
+    }
+    let threads = worker_threads(args.threads)?;
+    println!("Using {} threads", threads);
+    let seed = args
+        .seed
+        .unwrap_or_else(|| ChaCha8Rng::from_entropy().gen());
+    println!("Seed: {}", seed);
+
+    let mut walks = vec![];
+    let mut descents = vec![];
+    for &neighbourhood in &args.neighbourhoods {
+        println!(
+            "{} walks of {} steps in the {} neighbourhood",
+            args.walks, args.steps, neighbourhood
+        );
+        let stats = landscape::walks(
+            &distance_matrix,
+            neighbourhood,
+            args.walks,
+            args.steps,
+            args.preserve_first,
+            threads,
+            seed,
+            |lengths| WalkStats::new(lengths, args.max_lag, args.epsilon, EPSILON_STEPS),
+        );
+
+        let mean = |value: &dyn Fn(&WalkStats) -> Option<f64>| {
+            let values: Vec<f64> = stats.iter().filter_map(value).collect();
+            match values.is_empty() {
+                true => String::from("n/a"),
+                false => format!("{:.4}", values.iter().sum::<f64>() / values.len() as f64),
             }
+        };
+        println!(
+            "  Mean tour length: {}, std dev.: {}",
+            mean(&|walk| Some(walk.mean_len)),
+            mean(&|walk| Some(walk.std_dev_len))
+        );
+        println!(
+            "  Autocorrelation rho(1): {}, correlation length: {}",
+            mean(&|walk| walk.acf.get(1).copied()),
+            mean(&|walk| walk.correlation_length)
+        );
+        println!(
+            "  Information content: {}, partial information content: {} (epsilon {})",
+            mean(&|walk| Some(walk.information_content)),
+            mean(&|walk| Some(walk.partial_information_content)),
+            args.epsilon
+        );
+        println!(
+            "  Information stability: {}",
+            mean(&|walk| Some(walk.information_stability))
+        );
+        walks.push((neighbourhood, stats));
+
+        if args.descents > 0 {
+            let found = landscape::descents(
+                &distance_matrix,
+                neighbourhood,
+                args.descents,
+                args.preserve_first,
+                threads,
+                seed,
+            );
+            print_descents(&found);
+            descents.push((neighbourhood, found));
         }
     }
-}
 
-fn find_starting_point(
-    visited_starting: &Mutex<FxHashSet<Vec<u16>>>,
-    distance_matrix: &Vec<Vec<i32>>,
-    max_retries: usize,
-) -> Option<Vec<u16>> {
-    let mut visited_set = visited_starting
-        .lock()
-        .expect("Mutex poisoned, bailing out!");
-
-    let mut starting_solution = random_solution(distance_matrix.len() as u16, None, true);
-    let mut retries = 0;
-    while visited_set.contains(&starting_solution) {
-        retries += 1;
-        if retries > max_retries {
-            //can't find any new starting points, end thread
-            return None;
+    println!("Saving results...");
+    let output = Output::new(&args.out_dir, "walk")?;
+    save_walks(&walks, &output)?;
+    if !descents.is_empty() {
+        save_descents(&descents, &output)?;
+    }
+    if !args.no_plot {
+        println!("Plotting...");
+        plot_acf(&walks, &output.path("acf", "png"))?;
+        plot_information(&walks, &output.path("information", "png"))?;
+        if !descents.is_empty() {
+            plot_descents(&descents, &output.path("descents", "png"))?;
         }
-        starting_solution = random_solution(distance_matrix.len() as u16, None, true);
     }
+    Ok(())
+}
 
-    visited_set.insert(starting_solution.clone());
+fn print_descents(descents: &[Descent]) {
+    let steps: Vec<usize> = descents.iter().map(|descent| descent.moves.len()).collect();
+    let count = descents.len() as f64;
+    let improvement: i64 = descents
+        .iter()
+        .map(|descent| (descent.starting_len - descent.optimum_len()) as i64)
+        .sum();
+    println!(
+        "  {} descents: {:.2} improving steps on average (min {}, max {}), mean improvement {:.1} per step",
+        descents.len(),
+        steps.iter().sum::<usize>() as f64 / count,
+        steps.iter().min().unwrap_or(&0),
+        steps.iter().max().unwrap_or(&0),
+        improvement as f64 / steps.iter().sum::<usize>().max(1) as f64
+    );
+}
 
-    Some(starting_solution)
+fn run_stats(args: &StatsArgs) -> Result<(), Box<dyn Error>> {
+    let local_minimums = load_local_optima(&args.path)?;
+    println!("Loaded {} local optima", local_minimums.len());
+    print_estimates(&local_minimums);
+    println!(
+        "{}",
+        calculate_stats(&local_minimums, &args.analysis, args.seed)
+    );
+    Ok(())
 }
 
-fn save_results(
-    local_minimums: &Vec<(Vec<u16>, i32, i32)>,
-    visited_starting: &FxHashSet<Vec<u16>>,
-    alg_name: &str,
-) {
-    let dt = chrono::offset::Local::now().to_string();
-    let lopath = format!("{}_local_optima_{}.csv", alg_name, dt);
-    let sppath = format!("{}_starting_points_{}.csv", alg_name, dt);
-
-    let mut lo_file = File::create(&lopath).expect("Could not create file!");
-    let mut starting_points_file = File::create(&sppath).expect("Could not create file!");
-
-    starting_points_file.write("tour\n".as_bytes()).unwrap();
-    for e in visited_starting {
-        starting_points_file
-            .write_fmt(format_args!("{:?}\n", e))
-            .unwrap();
-    }
+fn run_plot(args: &PlotArgs) -> Result<(), Box<dyn Error>> {
+    let local_minimums = load_local_optima(&args.path)?;
+    let name = match &args.name {
+        Some(name) => name.clone(),
+        None => args
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("tsprandom")),
+    };
+    let output = Output::new(&args.output.out_dir, &name)?;
+    save_plots(
+        &local_minimums,
+        &name,
+        &args.analysis,
+        &args.output,
+        &output,
+        true,
+    )
+}
 
-    lo_file
-        .write("id;tour;tour_len;related_starting_points\n".as_bytes())
-        .unwrap();
-    for (i, (tour, len, sp)) in local_minimums.iter().enumerate() {
-        lo_file
-            .write_fmt(format_args!("{};{:?};{};{}\n", i, tour, *len, *sp))
-            .unwrap();
+/// Estimated number of local optima and the largest basins with their shares
+fn print_estimates(local_minimums: &[(Vec<u16>, LocalOptimum)]) {
+    let abundances: Vec<usize> = local_minimums
+        .iter()
+        .map(|(_, optimum)| optimum.related_starting_points as usize)
+        .collect();
+    println!("{}", estimate_optima(&abundances));
+
+    let shares = basin_shares(&abundances);
+    let mut largest: Vec<usize> = (0..abundances.len()).collect();
+    largest.sort_by_key(|&i| std::cmp::Reverse(abundances[i]));
+    println!("Largest basins (tour length, samples, share with 95% CI):");
+    for i in largest.into_iter().take(LARGEST_BASINS) {
+        let Estimate {
+            value,
+            ci: (low, high),
+        } = shares[i];
+        println!(
+            "  {} {} ({:.2}% [{:.2}%, {:.2}%])",
+            local_minimums[i].1.len,
+            abundances[i],
+            100.0 * value,
+            100.0 * low,
+            100.0 * high
+        );
     }
+}
 
-    let output = Command::new("python3")
-        .args(["vis.py", &lopath, "local_optima_hillclimb_graph"])
-        .output();
-    match output {
-        Ok(output) => match output.status.success() {
-            true => println!("Plot saved!"),
-            false => println!(
-                "Python drawing module returned an error!: {:?}",
-                output.stdout
-            ),
-        },
-        Err(_) => println!("Failed to run python process!"),
+fn worker_threads(threads: Option<usize>) -> Result<usize, Box<dyn Error>> {
+    match threads {
+        Some(0) => Err("--threads must be at least 1".into()),
+        Some(threads) => Ok(threads),
+        None => Ok(available_parallelism()?.get()),
     }
 }
 
-fn calculcate_stats(local_minimums: &Vec<(Vec<u16>, i32, i32)>, alg_name: &str) {
-    //calculate distances from node to best node and height differences between them
-    let mut distances = vec![0; local_minimums.len() - 1];
-    let mut height_diff = vec![0; local_minimums.len() - 1];
+/// Local optima sorted by tour length, ties broken by the tour
+fn sorted_optima(
+    local_minimums: impl IntoIterator<Item = (Vec<u16>, LocalOptimum)>,
+) -> LocalOptima {
+    let mut local_minimums: LocalOptima = local_minimums.into_iter().collect();
+    local_minimums
+        .sort_by(|(a, a_optimum), (b, b_optimum)| (a_optimum.len, a).cmp(&(b_optimum.len, b)));
+    local_minimums
+}
+
+fn read_instance(path: &str) -> Result<Vec<Vec<i32>>, Box<dyn Error>> {
+    let distance_matrix = if path.ends_with(".txt") {
+        simpleparser::parse_simple(path)
+    } else {
+        parse_tsp_file(path).map(|file| file.distance_matrix)
+    };
+    distance_matrix.map_err(|e| format!("Could not read {}: {}", path, e).into())
+}
 
-    let best = &local_minimums[0];
-    for i in 1..local_minimums.len() {
-        distances[i - 1] = cmp_permutations(&best.0, &local_minimums[i].0) as u64;
-        height_diff[i - 1] = (local_minimums[i].1 - best.1) as u64;
+/// Tours with the same edge set share a key: rotations always do, reversals only on symmetric instances
+fn canonical_key(tour: &[u16], symmetric: bool) -> Vec<u16> {
+    let tour = Tour::new(tour.to_vec()).expect("Local search returned an invalid tour");
+    match symmetric {
+        true => tour.canonical().into_inner(),
+        false => tour.canonical_rotation().into_inner(),
     }
+}
 
-    println!("Plotting...");
-    plot_corr(&distances, &height_diff, alg_name);
-
-    //expected values
-    let ed = distances.iter().sum::<u64>() / distances.len() as u64;
-    let eh = height_diff.iter().sum::<u64>() / height_diff.len() as u64;
-    //expected value of products of the two variables
-    let ep = distances
+/// Distances of the other optima to the best one and their tour length differences
+fn distances_to_best(
+    local_minimums: &[(Vec<u16>, LocalOptimum)],
+    metric: TourMetric,
+) -> (Vec<u64>, Vec<u64>) {
+    let (best, others) = match local_minimums.split_first() {
+        Some((best, others)) => (best, others),
+        None => return (vec![], vec![]),
+    };
+    let distances = others
         .iter()
-        .zip(&height_diff)
-        .map(|(a, b)| a * b)
-        .sum::<u64>()
-        / distances.len() as u64;
-    //calculate covariance
-    let cov = ep - (ed * eh);
-
-    let mean_d_squared = distances.iter().map(|x| x * x).sum::<u64>() / distances.len() as u64;
-    let mean_h_squared = height_diff.iter().map(|x| x * x).sum::<u64>() / height_diff.len() as u64;
+        .map(|lo| metric.distance(&best.0, &lo.0) as u64)
+        .collect();
+    let height_diff = others
+        .iter()
+        .map(|lo| (lo.1.len - best.1.len) as u64)
+        .collect();
+    (distances, height_diff)
+}
 
-    let std_d = ((mean_d_squared - (ed * ed)) as f64).sqrt();
-    let std_h = ((mean_h_squared - (eh * eh)) as f64).sqrt();
+fn calculate_stats(
+    local_minimums: &[(Vec<u16>, LocalOptimum)],
+    analysis: &AnalysisArgs,
+    seed: Option<u64>,
+) -> FdcResult {
+    let (distances, height_diff) = distances_to_best(local_minimums, analysis.distance);
+    let distances: Vec<f64> = distances.iter().map(|&d| d as f64).collect();
+    let height_diff: Vec<f64> = height_diff.iter().map(|&h| h as f64).collect();
+    fitness_distance_correlation(&distances, &height_diff, analysis.bootstrap, seed)
+}
 
-    let cor = cov as f64 / (std_d * std_h);
+/// Writes the graph files, plus the correlation plot and the html view if `plot` is set
+fn save_plots(
+    local_minimums: &[(Vec<u16>, LocalOptimum)],
+    name: &str,
+    analysis: &AnalysisArgs,
+    output_args: &OutputArgs,
+    output: &Output,
+    plot: bool,
+) -> Result<(), Box<dyn Error>> {
+    println!("Saving graph...");
+    let graph = OptimaGraph::star(local_minimums, analysis.distance);
+    save_graph(&graph, output, "local_optima_graph", &output_args.formats)?;
+    if !plot {
+        return Ok(());
+    }
 
-    println!("Mean distance:{}, Mean height difference:{}", ed, eh);
-    println!(
-        "std dev. of distance:{}, std dev. of height diff:{}",
-        std_d, std_h
-    );
-    println!("Covariance: {}\nCorrelation:{}", cov, cor);
+    let html_path = output.path("local_optima_graph", "html");
+    save_html(
+        &graph,
+        output_args.top_n,
+        &format!("{} local optima", name),
+        &html_path,
+    )
+    .map_err(|e| format!("Could not write {}: {}", html_path.display(), e))?;
+
+    let (distances, height_diff) = distances_to_best(local_minimums, analysis.distance);
+    if !distances.is_empty() {
+        println!("Plotting...");
+        plot_corr(
+            &distances,
+            &height_diff,
+            analysis.distance,
+            &output.path("corr", "png"),
+        )?;
+    }
+    Ok(())
 }
 
-fn plot_corr(distances: &Vec<u64>, height_diff: &Vec<u64>, alg_name: &str) {
-    let dt = chrono::offset::Local::now().to_string();
-    let path = format!("{}_corr_{}.png", alg_name, dt);
-    let root_area = BitMapBackend::new(&path, (800, 400)).into_drawing_area();
-    root_area.fill(&WHITE).unwrap();
+fn plot_corr(
+    distances: &[u64],
+    height_diff: &[u64],
+    metric: TourMetric,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let root_area = BitMapBackend::new(path, (800, 400)).into_drawing_area();
+    root_area.fill(&WHITE)?;
 
     let max_height = height_diff[height_diff.len() - 1] + 100;
     let max_dist = *distances.iter().max().unwrap() + 2;
     let mut min_dist = *distances.iter().min().unwrap();
 
-    if min_dist >= 2{
+    if min_dist >= 2 {
         min_dist -= 2;
     }
 
-    let x_range = min_dist..max_dist as u64;
-    let y_range = 0..max_height as u64;
+    let x_range = min_dist..max_dist;
+    let y_range = 0..max_height;
 
     let mut ctx = ChartBuilder::on(&root_area)
         .set_label_area_size(LabelAreaPosition::Left, 40)
         .set_label_area_size(LabelAreaPosition::Bottom, 40)
-        .build_cartesian_2d(x_range, y_range)
-        .unwrap();
+        .build_cartesian_2d(x_range, y_range)?;
 
     ctx.configure_mesh()
-        .x_desc("Distance form best solution (in pair swaps)")
+        .x_desc(format!(
+            "Distance from best solution (in {})",
+            metric.unit()
+        ))
         .y_desc("Path length difference from best solution")
-        .draw()
-        .unwrap();
+        .draw()?;
 
     ctx.draw_series(
         distances
             .iter()
             .zip(height_diff)
-            .map(|(x, y)| Circle::new((*x, *y), 5, &BLUE)),
-    )
-    .unwrap();
+            .map(|(x, y)| Circle::new((*x, *y), 5, BLUE)),
+    )?;
+
+    root_area.present()?;
+    Ok(())
+}
+
+/// Mean autocorrelation function of every neighbourhood
+fn plot_acf(walks: &[(Neighbourhood, Vec<WalkStats>)], path: &Path) -> Result<(), Box<dyn Error>> {
+    let acfs: Vec<(Neighbourhood, Vec<f64>)> = walks
+        .iter()
+        .map(|(neighbourhood, stats)| (*neighbourhood, mean_acf(stats)))
+        .collect();
+    let max_lag = acfs
+        .iter()
+        .map(|(_, acf)| acf.len())
+        .max()
+        .unwrap_or(1)
+        .max(2)
+        - 1;
+    let min_rho = acfs
+        .iter()
+        .flat_map(|(_, acf)| acf.iter().copied())
+        .fold(0.0, f64::min);
+
+    let root_area = BitMapBackend::new(path, (800, 400)).into_drawing_area();
+    root_area.fill(&WHITE)?;
+    let mut ctx = ChartBuilder::on(&root_area)
+        .margin(10)
+        .set_label_area_size(LabelAreaPosition::Left, 50)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(0..max_lag, min_rho..1.0)?;
+    ctx.configure_mesh()
+        .x_desc("Lag (steps)")
+        .y_desc("Autocorrelation")
+        .draw()?;
+
+    for (i, (neighbourhood, acf)) in acfs.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        ctx.draw_series(LineSeries::new(
+            acf.iter().enumerate().map(|(lag, &rho)| (lag, rho)),
+            color,
+        ))?
+        .label(neighbourhood.name())
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    ctx.configure_series_labels()
+        .background_style(WHITE)
+        .border_style(BLACK)
+        .draw()?;
+
+    root_area.present()?;
+    Ok(())
+}
+
+/// Tour length over the steps of every descent, one colour per neighbourhood
+fn plot_descents(
+    descents: &[(Neighbourhood, Vec<Descent>)],
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let curves: Vec<(Neighbourhood, Vec<Vec<i32>>)> = descents
+        .iter()
+        .map(|(neighbourhood, found)| {
+            let curves = found
+                .iter()
+                .map(|descent| descent_curve(descent.starting_len, &descent.moves))
+                .collect();
+            (*neighbourhood, curves)
+        })
+        .collect();
+    let all = || curves.iter().flat_map(|(_, curves)| curves);
+    let max_steps = all().map(|curve| curve.len()).max().unwrap_or(1).max(2) - 1;
+    let min_len = all().flatten().copied().min().unwrap_or(0);
+    let max_len = all().flatten().copied().max().unwrap_or(0).max(min_len + 1);
+
+    let root_area = BitMapBackend::new(path, (800, 400)).into_drawing_area();
+    root_area.fill(&WHITE)?;
+    let mut ctx = ChartBuilder::on(&root_area)
+        .margin(10)
+        .set_label_area_size(LabelAreaPosition::Left, 60)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(0..max_steps, min_len..max_len)?;
+    ctx.configure_mesh()
+        .x_desc("Improving steps")
+        .y_desc("Tour length")
+        .draw()?;
+
+    for (i, (neighbourhood, curves)) in curves.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        for (k, curve) in curves.iter().enumerate() {
+            let series = ctx.draw_series(LineSeries::new(
+                curve.iter().enumerate().map(|(step, &len)| (step, len)),
+                color.mix(0.4),
+            ))?;
+            if k == 0 {
+                series
+                    .label(neighbourhood.name())
+                    .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+            }
+        }
+    }
+    ctx.configure_series_labels()
+        .background_style(WHITE)
+        .border_style(BLACK)
+        .draw()?;
+
+    root_area.present()?;
+    Ok(())
+}
+
+/// Mean information content and partial information content of every neighbourhood over epsilon
+fn plot_information(
+    walks: &[(Neighbourhood, Vec<WalkStats>)],
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let root_area = BitMapBackend::new(path, (800, 400)).into_drawing_area();
+    root_area.fill(&WHITE)?;
+    let mut ctx = ChartBuilder::on(&root_area)
+        .margin(10)
+        .set_label_area_size(LabelAreaPosition::Left, 50)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(0.0..1.0, 0.0..1.0)?;
+    ctx.configure_mesh()
+        .x_desc("Epsilon relative to the information stability")
+        .y_desc("Information content")
+        .draw()?;
+
+    for (i, (neighbourhood, stats)) in walks.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let curve = mean_information_curve(stats);
+        ctx.draw_series(LineSeries::new(
+            curve.iter().map(|&(epsilon, (h, _))| (epsilon, h)),
+            color,
+        ))?
+        .label(format!("{} H", neighbourhood))
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        //partial information content with markers on the line
+        ctx.draw_series(
+            LineSeries::new(
+                curve.iter().map(|&(epsilon, (_, m))| (epsilon, m)),
+                color.filled(),
+            )
+            .point_size(2),
+        )?
+        .label(format!("{} M", neighbourhood))
+        .legend(move |(x, y)| Circle::new((x + 10, y), 2, color.filled()));
+    }
+    ctx.configure_series_labels()
+        .background_style(WHITE)
+        .border_style(BLACK)
+        .draw()?;
 
-    root_area.present().unwrap();
+    root_area.present()?;
+    Ok(())
 }
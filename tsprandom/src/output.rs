@@ -0,0 +1,431 @@
+use std::{
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    mem,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use flate2::{write::GzEncoder, Compression};
+use rustc_hash::FxHashMap;
+use tsptools::tour_distance::TourMetric;
+
+use crate::{
+    estimators::basin_shares,
+    export::{GraphFormat, OptimaGraph},
+    landscape::{mean_acf, mean_information_curve, Descent, Neighbourhood, WalkStats},
+    lon::Lon,
+    LocalOptima, LocalOptimum,
+};
+
+/// Names every file of one run `{name}_{kind}_{timestamp}.{extension}` inside the output directory
+pub struct Output {
+    dir: PathBuf,
+    name: String,
+    timestamp: String,
+}
+
+impl Output {
+    pub fn new(dir: &Path, name: &str) -> Result<Self, Box<dyn Error>> {
+        Self::resume(dir, name, &chrono::offset::Local::now().to_string())
+    }
+
+    /// Output of an earlier run, so that a resumed run keeps writing to the same files
+    pub fn resume(dir: &Path, name: &str, timestamp: &str) -> Result<Self, Box<dyn Error>> {
+        //absolute, so that a checkpoint can be resumed from another working directory
+        let dir = fs::create_dir_all(dir)
+            .and_then(|_| fs::canonicalize(dir))
+            .map_err(|e| format!("Could not create output directory {}: {}", dir.display(), e))?;
+        Ok(Output {
+            dir,
+            name: name.to_owned(),
+            timestamp: timestamp.to_owned(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    pub fn path(&self, kind: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}_{}_{}.{}",
+            self.name, kind, self.timestamp, extension
+        ))
+    }
+
+    pub fn create(&self, kind: &str, extension: &str) -> Result<BufWriter<File>, Box<dyn Error>> {
+        let path = self.path(kind, extension);
+        let file = File::create(&path)
+            .map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+        Ok(BufWriter::new(file))
+    }
+}
+
+/// How the starting points are saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartingPointsDump {
+    Csv,
+    Gzip,
+    Skip,
+}
+
+impl StartingPointsDump {
+    pub const ALL: [StartingPointsDump; 3] = [
+        StartingPointsDump::Csv,
+        StartingPointsDump::Gzip,
+        StartingPointsDump::Skip,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Gzip => "gzip",
+            Self::Skip => "none",
+        }
+    }
+
+    fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::Csv => Some("csv"),
+            Self::Gzip => Some("csv.gz"),
+            Self::Skip => None,
+        }
+    }
+}
+
+impl FromStr for StartingPointsDump {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "gzip" => Ok(Self::Gzip),
+            "none" => Ok(Self::Skip),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+/// Starting points csv written while sampling, so they never have to be kept in memory.
+/// The gzip file is a series of gzip members, every checkpoint ends one.
+pub enum StartingPointsWriter {
+    Csv(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl StartingPointsWriter {
+    pub fn create(
+        output: &Output,
+        dump: StartingPointsDump,
+    ) -> Result<Option<Self>, Box<dyn Error>> {
+        let Some(extension) = dump.extension() else {
+            return Ok(None);
+        };
+        let mut writer = Self::new(dump, output.create("starting_points", extension)?);
+        writer.write_all(b"tour\n")?;
+        Ok(Some(writer))
+    }
+
+    /// Reopens the file of a resumed run, dropping what was written after its checkpoint
+    pub fn resume(
+        output: &Output,
+        dump: StartingPointsDump,
+        len: u64,
+    ) -> Result<Option<Self>, Box<dyn Error>> {
+        let Some(extension) = dump.extension() else {
+            return Ok(None);
+        };
+        let path = output.path("starting_points", extension);
+        let reopen = || -> io::Result<File> {
+            let mut file = OpenOptions::new().write(true).open(&path)?;
+            file.set_len(len)?;
+            file.seek(SeekFrom::End(0))?;
+            Ok(file)
+        };
+        let file = reopen().map_err(|e| format!("Could not reopen {}: {}", path.display(), e))?;
+        Ok(Some(Self::new(dump, BufWriter::new(file))))
+    }
+
+    fn new(dump: StartingPointsDump, writer: BufWriter<File>) -> Self {
+        match dump {
+            StartingPointsDump::Gzip => Self::Gzip(GzEncoder::new(writer, Compression::default())),
+            _ => Self::Csv(writer),
+        }
+    }
+
+    pub fn write_tour(&mut self, tour: &[u16]) -> io::Result<()> {
+        writeln!(self, "{:?}", tour)
+    }
+
+    /// Writes out everything so far and returns the length of the file
+    pub fn checkpoint(&mut self) -> io::Result<u64> {
+        match self {
+            Self::Csv(writer) => {
+                writer.flush()?;
+                writer.get_mut().stream_position()
+            }
+            Self::Gzip(encoder) => {
+                //the clone shares the file position, the next member starts where this one ends
+                let file = encoder.get_ref().get_ref().try_clone()?;
+                let next = GzEncoder::new(BufWriter::new(file), Compression::default());
+                let mut file = mem::replace(encoder, next)
+                    .finish()?
+                    .into_inner()
+                    .map_err(|e| e.into_error())?;
+                file.stream_position()
+            }
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Csv(mut writer) => writer.flush(),
+            Self::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for StartingPointsWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Csv(writer) => writer.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Csv(writer) => writer.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+pub fn save_results(
+    local_minimums: &[(Vec<u16>, LocalOptimum)],
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let mut lo_file = output.create("local_optima", "csv")?;
+    let mut variants_file = output.create("local_optima_variants", "csv")?;
+
+    lo_file.write_all(b"id;tour;tour_len;related_starting_points;variants\n")?;
+    variants_file.write_all(b"local_optimum_id;tour;related_starting_points\n")?;
+    for (i, (tour, optimum)) in local_minimums.iter().enumerate() {
+        writeln!(
+            lo_file,
+            "{};{:?};{};{};{}",
+            i,
+            tour,
+            optimum.len,
+            optimum.related_starting_points,
+            optimum.variants.len()
+        )?;
+        //sorted, the map order differs between runs and resumed runs
+        let mut variants: Vec<_> = optimum.variants.iter().collect();
+        variants.sort_unstable();
+        for (variant, count) in variants {
+            writeln!(variants_file, "{};{:?};{}", i, variant, count)?;
+        }
+    }
+
+    lo_file.flush()?;
+    variants_file.flush()?;
+    Ok(())
+}
+
+/// Share of the samples that reached every optimum, ids as in the local optima csv
+pub fn save_basins(
+    local_minimums: &[(Vec<u16>, LocalOptimum)],
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let abundances: Vec<usize> = local_minimums
+        .iter()
+        .map(|(_, optimum)| optimum.related_starting_points as usize)
+        .collect();
+    let mut file = output.create("basins", "csv")?;
+
+    file.write_all(b"local_optimum_id;tour_len;related_starting_points;share;ci_low;ci_high\n")?;
+    for (i, share) in basin_shares(&abundances).into_iter().enumerate() {
+        writeln!(
+            file,
+            "{};{};{};{};{};{}",
+            i, local_minimums[i].1.len, abundances[i], share.value, share.ci.0, share.ci.1
+        )?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Reads a local optima csv written by [`save_results`], sorted by tour length.
+/// The variants are not part of that file and stay empty.
+pub fn load_local_optima(path: &Path) -> Result<LocalOptima, Box<dyn Error>> {
+    let file = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+    let mut local_minimums = vec![];
+    for (i, line) in file.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = || format!("{}:{}: invalid local optimum", path.display(), i + 1);
+        let fields: Vec<&str> = line.split(';').collect();
+        if fields.len() < 4 {
+            return Err(invalid().into());
+        }
+        let tour = fields[1]
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(',')
+            .map(|city| city.trim().parse())
+            .collect::<Result<Vec<u16>, _>>()
+            .map_err(|_| invalid())?;
+        let optimum = LocalOptimum {
+            len: fields[2].parse().map_err(|_| invalid())?,
+            related_starting_points: fields[3].parse().map_err(|_| invalid())?,
+            variants: FxHashMap::default(),
+        };
+        local_minimums.push((tour, optimum));
+    }
+
+    local_minimums.sort_by_key(|(_, optimum)| optimum.len);
+    Ok(local_minimums)
+}
+
+pub fn save_graph(
+    graph: &OptimaGraph,
+    output: &Output,
+    kind: &str,
+    formats: &[GraphFormat],
+) -> Result<(), Box<dyn Error>> {
+    for &format in formats {
+        let path = output.path(kind, format.extension());
+        graph
+            .save(&path, format)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+pub fn save_lon(
+    lon: &Lon,
+    local_minimums: &[(Vec<u16>, LocalOptimum)],
+    metric: TourMetric,
+    output: &Output,
+    formats: &[GraphFormat],
+) -> Result<(), Box<dyn Error>> {
+    let mut nodes_file = output.create("lon_nodes", "csv")?;
+    let mut edges_file = output.create("lon_edges", "csv")?;
+
+    nodes_file.write_all(b"id;tour;tour_len;sampled\n")?;
+    for (i, (tour, len)) in lon.nodes.iter().enumerate() {
+        writeln!(nodes_file, "{};{:?};{};{}", i, tour, len, i < lon.sampled)?;
+    }
+
+    let mut edges: Vec<_> = lon.edges.iter().collect();
+    edges.sort();
+    edges_file.write_all(b"source;target;weight\n")?;
+    for ((source, target), weight) in edges {
+        writeln!(edges_file, "{};{};{}", source, target, weight)?;
+    }
+    nodes_file.flush()?;
+    edges_file.flush()?;
+
+    let graph = OptimaGraph::from_lon(lon, local_minimums, metric);
+    save_graph(&graph, output, "lon_graph", formats)
+}
+
+/// Walk measures of every neighbourhood: one row per walk, the mean autocorrelation function and
+/// the mean information content curve
+pub fn save_walks(
+    walks: &[(Neighbourhood, Vec<WalkStats>)],
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let mut summary_file = output.create("summary", "csv")?;
+    let mut acf_file = output.create("acf", "csv")?;
+    let mut information_file = output.create("information", "csv")?;
+
+    summary_file.write_all(
+        b"neighbourhood;walk;mean_len;std_dev_len;autocorrelation_1;correlation_length;\
+information_content;partial_information_content;information_stability\n",
+    )?;
+    acf_file.write_all(b"neighbourhood;lag;autocorrelation\n")?;
+    information_file.write_all(
+        b"neighbourhood;relative_epsilon;information_content;partial_information_content\n",
+    )?;
+    for (neighbourhood, stats) in walks {
+        for (i, walk) in stats.iter().enumerate() {
+            writeln!(
+                summary_file,
+                "{};{};{};{};{};{};{};{};{}",
+                neighbourhood,
+                i,
+                walk.mean_len,
+                walk.std_dev_len,
+                walk.acf.get(1).map(f64::to_string).unwrap_or_default(),
+                walk.correlation_length
+                    .map(|length| length.to_string())
+                    .unwrap_or_default(),
+                walk.information_content,
+                walk.partial_information_content,
+                walk.information_stability
+            )?;
+        }
+        for (lag, rho) in mean_acf(stats).into_iter().enumerate() {
+            writeln!(acf_file, "{};{};{}", neighbourhood, lag, rho)?;
+        }
+        for (epsilon, (h, m)) in mean_information_curve(stats) {
+            writeln!(
+                information_file,
+                "{};{};{};{}",
+                neighbourhood, epsilon, h, m
+            )?;
+        }
+    }
+
+    summary_file.flush()?;
+    acf_file.flush()?;
+    information_file.flush()?;
+    Ok(())
+}
+
+/// Writes one row per descent and one per accepted move of the descents
+pub fn save_descents(
+    descents: &[(Neighbourhood, Vec<Descent>)],
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let mut descents_file = output.create("descents", "csv")?;
+    let mut moves_file = output.create("moves", "csv")?;
+
+    descents_file.write_all(b"neighbourhood;descent;starting_len;optimum_len;improving_steps\n")?;
+    moves_file.write_all(b"neighbourhood;descent;step;i;j;delta;tour_len\n")?;
+    for (neighbourhood, descents) in descents {
+        for (k, descent) in descents.iter().enumerate() {
+            writeln!(
+                descents_file,
+                "{};{};{};{};{}",
+                neighbourhood,
+                k,
+                descent.starting_len,
+                descent.optimum_len(),
+                descent.moves.len()
+            )?;
+            for step in &descent.moves {
+                writeln!(
+                    moves_file,
+                    "{};{};{};{};{};{};{}",
+                    neighbourhood, k, step.step, step.i, step.j, step.delta, step.len
+                )?;
+            }
+        }
+    }
+
+    descents_file.flush()?;
+    moves_file.flush()?;
+    Ok(())
+}
@@ -0,0 +1,472 @@
+//! Parallel sampling of local optima that only depends on the seed: sample `i` draws its
+//! starting point and the seed of the local search from its own ChaCha stream, and the
+//! samples are merged in index order.
+
+use std::{
+    error::Error,
+    ops::Range,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::Instant,
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rustc_hash::FxHashMap;
+use tsptools::{
+    helpers::random_solution,
+    solver::{solver_by_name, Budget, CancelToken, Solver},
+};
+
+use crate::{
+    canonical_key,
+    cli::SampleArgs,
+    dedup::{Dedup, VisitedSet},
+    shards::{shard_of, Shards, SHARD_COUNT},
+    LocalOptimum,
+};
+
+/// Samples run in parallel between two merges. Fixed, so that the starting points a sample
+/// can collide with do not depend on the number of threads.
+const BLOCK_SIZE: usize = 1024;
+
+/// State of a sampling run. Together with the seed it is all that is needed to continue the run.
+pub struct Sampling {
+    pub local_minimums: Shards<FxHashMap<Vec<u16>, LocalOptimum>>,
+    pub visited_starting: Shards<VisitedSet>,
+    /// Index of the next sample, which selects its random stream
+    pub next_index: usize,
+    /// Samples that reached a local optimum
+    pub samples: usize,
+    /// Samples dropped because no unused starting point was found within the retries
+    pub lost: usize,
+    /// Sampling stopped after a block in which every sample was lost
+    pub exhausted: bool,
+    /// Sampling stopped at the deadline
+    pub timed_out: bool,
+    /// Sampling stopped on request
+    pub interrupted: bool,
+}
+
+impl Sampling {
+    /// Empty state, `target` sizes the Bloom filters
+    pub fn new(dedup: Dedup, target: usize, bloom_fp: f64) -> Self {
+        //room for the uneven spread of the samples over the shards
+        let per_shard = target / SHARD_COUNT;
+        let expected = per_shard + 3 * (per_shard as f64).sqrt() as usize + 16;
+        Sampling {
+            local_minimums: Shards::new(),
+            visited_starting: Shards::from_fn(|| VisitedSet::new(dedup, expected, bloom_fp)),
+            next_index: 0,
+            samples: 0,
+            lost: 0,
+            exhausted: false,
+            timed_out: false,
+            interrupted: false,
+        }
+    }
+}
+
+/// Reasons to stop before the target is reached, checked before every sample
+pub struct Stop<'a> {
+    pub deadline: Option<Instant>,
+    pub interrupted: &'a AtomicBool,
+}
+
+impl Stop<'_> {
+    fn now(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Starting point and the local optimum reached from it
+struct Sample {
+    start: Vec<u16>,
+    start_shard: usize,
+    key: Vec<u16>,
+    key_shard: usize,
+    tour: Vec<u16>,
+    len: i32,
+}
+
+impl Sample {
+    fn new(start: Vec<u16>, (tour, len): (Vec<u16>, i32), symmetric: bool) -> Self {
+        let key = canonical_key(&tour, symmetric);
+        Sample {
+            start_shard: shard_of(&start),
+            start,
+            key_shard: shard_of(&key),
+            key,
+            tour,
+            len,
+        }
+    }
+}
+
+/// Sample checked against the starting points of the previous blocks only
+struct Candidate {
+    rng: ChaCha8Rng,
+    retries: usize,
+    sample: Option<Sample>,
+}
+
+/// Continues sampling until `target` samples reached a local optimum, `stop` says so or a whole
+/// block finds no unused starting point. After every block `on_block` gets the state and the
+/// starting points of the block in index order.
+///
+/// A block cut short by `stop` is merged as it is, so a run resumed after it can differ from
+/// an uninterrupted one when a sample after the cut draws the starting point of one before it.
+#[allow(clippy::too_many_arguments)]
+pub fn sample<F>(
+    sampling: &mut Sampling,
+    args: &SampleArgs,
+    distance_matrix: &[Vec<i32>],
+    symmetric: bool,
+    threads: usize,
+    seed: u64,
+    target: usize,
+    stop: &Stop,
+    mut on_block: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&Sampling, &[&[u16]]) -> Result<(), Box<dyn Error>>,
+{
+    let mut solver = new_solver(&args.alg, args.preserve_first);
+
+    //lost samples are replaced by samples with new indices
+    while sampling.samples < target
+        && !sampling.exhausted
+        && !sampling.timed_out
+        && !sampling.interrupted
+    {
+        let block_size = BLOCK_SIZE.min(target - sampling.samples);
+        let block = sampling.next_index..sampling.next_index + block_size;
+
+        let mut candidates = run_block(
+            block,
+            args,
+            distance_matrix,
+            &sampling.visited_starting,
+            symmetric,
+            threads,
+            seed,
+            stop,
+        );
+        sampling.next_index += candidates.len();
+        if candidates.len() < block_size {
+            sampling.interrupted = stop.interrupted.load(Ordering::Relaxed);
+            sampling.timed_out = !sampling.interrupted;
+        }
+
+        let mut collided: Vec<usize> = sampling
+            .visited_starting
+            .for_each_parallel(threads, |shard, visited| {
+                samples_in_shard(&candidates, |sample| sample.start_shard == shard)
+                    .filter(|(_, sample)| !visited.insert(&sample.start))
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+            .collect();
+        collided.sort_unstable();
+
+        //an earlier sample of this block took the same starting point
+        for i in collided {
+            let candidate = &mut candidates[i];
+            candidate.sample = draw_start(
+                &mut candidate.rng,
+                &mut candidate.retries,
+                &sampling.visited_starting,
+                distance_matrix.len(),
+                args,
+            )
+            .map(|start| {
+                let solution =
+                    local_search(solver.as_mut(), &mut candidate.rng, &start, distance_matrix);
+                Sample::new(start, solution, symmetric)
+            });
+            if let Some(sample) = &candidate.sample {
+                sampling
+                    .visited_starting
+                    .get_mut(&sample.start)
+                    .insert(&sample.start);
+            }
+        }
+
+        sampling
+            .local_minimums
+            .for_each_parallel(threads, |shard, optima| {
+                for (_, sample) in samples_in_shard(&candidates, |sample| sample.key_shard == shard)
+                {
+                    let optimum =
+                        optima
+                            .entry(sample.key.clone())
+                            .or_insert_with(|| LocalOptimum {
+                                len: sample.len,
+                                related_starting_points: 0,
+                                variants: FxHashMap::default(),
+                            });
+                    optimum.related_starting_points += 1;
+                    *optimum.variants.entry(sample.tour.clone()).or_insert(0) += 1;
+                }
+            });
+
+        let lost = candidates.iter().filter(|c| c.sample.is_none()).count();
+        sampling.samples += candidates.len() - lost;
+        sampling.lost += lost;
+        sampling.exhausted = lost == candidates.len() && lost > 0;
+
+        let starts: Vec<&[u16]> = samples_in_shard(&candidates, |_| true)
+            .map(|(_, sample)| sample.start.as_slice())
+            .collect();
+        on_block(sampling, &starts)?;
+    }
+
+    Ok(())
+}
+
+/// Samples of the block in index order that belong to a shard
+fn samples_in_shard<'a, F: Fn(&Sample) -> bool + 'a>(
+    candidates: &'a [Candidate],
+    in_shard: F,
+) -> impl Iterator<Item = (usize, &'a Sample)> + 'a {
+    candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| c.sample.as_ref().map(|sample| (i, sample)))
+        .filter(move |(_, sample)| in_shard(sample))
+}
+
+/// Runs the samples of the block, or the ones started before `stop` said so.
+/// Samples are started in index order, so the result is always a prefix of the block.
+#[allow(clippy::too_many_arguments)]
+fn run_block(
+    block: Range<usize>,
+    args: &SampleArgs,
+    distance_matrix: &[Vec<i32>],
+    visited_starting: &Shards<VisitedSet>,
+    symmetric: bool,
+    threads: usize,
+    seed: u64,
+    stop: &Stop,
+) -> Vec<Candidate> {
+    let next_sample = AtomicUsize::new(block.start);
+
+    let mut candidates: Vec<(usize, Candidate)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut solver = new_solver(&args.alg, args.preserve_first);
+                    let mut found = vec![];
+                    loop {
+                        if stop.now() {
+                            break;
+                        }
+                        let i = next_sample.fetch_add(1, Ordering::Relaxed);
+                        if i >= block.end {
+                            break;
+                        }
+                        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                        rng.set_stream(i as u64);
+
+                        let mut retries = 0;
+                        let sample = draw_start(
+                            &mut rng,
+                            &mut retries,
+                            visited_starting,
+                            distance_matrix.len(),
+                            args,
+                        )
+                        .map(|start| {
+                            let solution =
+                                local_search(solver.as_mut(), &mut rng, &start, distance_matrix);
+                            Sample::new(start, solution, symmetric)
+                        });
+                        found.push((
+                            i,
+                            Candidate {
+                                rng,
+                                retries,
+                                sample,
+                            },
+                        ));
+                    }
+                    found
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("Sampling thread panicked"))
+            .collect()
+    });
+
+    candidates.sort_by_key(|(i, _)| *i);
+    candidates
+        .into_iter()
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+pub fn new_solver(alg: &str, preserve_first: bool) -> Box<dyn Solver> {
+    let mut solver = solver_by_name(alg).expect("Invalid algorithm param");
+    solver
+        .configure("preserve_first", &preserve_first.to_string())
+        .expect("Algorithm does not support preserve_first");
+    solver
+}
+
+/// Random starting point not in `visited_starting`, None once the retries are used up
+fn draw_start(
+    rng: &mut ChaCha8Rng,
+    retries: &mut usize,
+    visited_starting: &Shards<VisitedSet>,
+    node_count: usize,
+    args: &SampleArgs,
+) -> Option<Vec<u16>> {
+    loop {
+        let start = random_solution(node_count as u16, Some(rng.gen()), args.preserve_first);
+        if !visited_starting.get(&start).contains(&start) {
+            return Some(start);
+        }
+        *retries += 1;
+        if *retries > args.max_retries {
+            return None;
+        }
+    }
+}
+
+/// Runs the local search from `start`, seeding it from `rng`
+pub fn local_search(
+    solver: &mut dyn Solver,
+    rng: &mut ChaCha8Rng,
+    start: &[u16],
+    distance_matrix: &[Vec<i32>],
+) -> (Vec<u16>, i32) {
+    solver
+        .configure("seed", &rng.gen::<u64>().to_string())
+        .expect("Algorithm does not support seed");
+    let solution = solver.solve(
+        distance_matrix,
+        Some(start),
+        &Budget::unlimited(),
+        &CancelToken::new(),
+        &mut |_| {},
+    );
+    (solution.tour, solution.len)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::{
+        cli::{Cli, Command},
+        test_utils::skewed_matrix,
+    };
+
+    fn sample_args(args: &[&str]) -> SampleArgs {
+        let command = ["tsprandom", "sample", "instance.txt", "-a", "2opt"];
+        match Cli::parse_from(command.iter().chain(args)).command {
+            Command::Sample(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    fn run(args: &SampleArgs, n: i32, threads: usize, target: usize) -> (Sampling, Vec<Vec<u16>>) {
+        let distance_matrix = skewed_matrix(n as usize, Some(17));
+        let mut sampling = Sampling::new(args.dedup, target, args.bloom_fp);
+        let interrupted = AtomicBool::new(false);
+        let stop = Stop {
+            deadline: None,
+            interrupted: &interrupted,
+        };
+        let mut starts = vec![];
+        sample(
+            &mut sampling,
+            args,
+            &distance_matrix,
+            true,
+            threads,
+            5,
+            target,
+            &stop,
+            |_, block| {
+                starts.extend(block.iter().map(|start| start.to_vec()));
+                Ok(())
+            },
+        )
+        .unwrap();
+        (sampling, starts)
+    }
+
+    /// Key, length, related starting points and sorted variants of an optimum
+    type Optimum = (Vec<u16>, i32, i32, Vec<(Vec<u16>, i32)>);
+
+    /// Local optima in a comparable order
+    fn optima(sampling: &Sampling) -> Vec<Optimum> {
+        let mut optima: Vec<_> = sampling
+            .local_minimums
+            .iter()
+            .flatten()
+            .map(|(key, optimum)| {
+                let mut variants: Vec<_> = optimum.variants.clone().into_iter().collect();
+                variants.sort_unstable();
+                let (len, related) = (optimum.len, optimum.related_starting_points);
+                (key.clone(), len, related, variants)
+            })
+            .collect();
+        optima.sort_unstable();
+        optima
+    }
+
+    #[test]
+    fn results_do_not_depend_on_thread_count() {
+        //more samples than a block and than the 6! starting points, so samples collide and get lost
+        let args = sample_args(&["--max-retries", "3"]);
+        let (single, single_starts) = run(&args, 7, 1, 1500);
+        let (multi, multi_starts) = run(&args, 7, 4, 1500);
+
+        assert_eq!(optima(&single), optima(&multi));
+        assert_eq!(single_starts, multi_starts);
+        assert_eq!(
+            (single.samples, single.lost, single.next_index),
+            (multi.samples, multi.lost, multi.next_index)
+        );
+        assert!(single.lost > 0);
+    }
+
+    #[test]
+    fn samples_are_counted_exactly() {
+        //city 0 stays first, so there are 5! = 120 starting points
+        let args = sample_args(&["--max-retries", "2000"]);
+        let (sampling, starts) = run(&args, 6, 3, 100);
+        assert_eq!(sampling.samples, 100);
+        assert_eq!(starts.len(), 100);
+        assert_eq!(sampling.samples + sampling.lost, sampling.next_index);
+        assert!(!sampling.exhausted);
+
+        let (sampling, starts) = run(&args, 6, 3, 200);
+        assert_eq!(sampling.samples, 120);
+        assert_eq!(sampling.samples + sampling.lost, sampling.next_index);
+        assert!(sampling.lost > 0 && sampling.exhausted);
+        let basins: i32 = optima(&sampling).iter().map(|optimum| optimum.2).sum();
+        assert_eq!(basins, 120);
+        let mut distinct = starts.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 120);
+
+        let args = sample_args(&["--max-retries", "0"]);
+        let (sampling, _) = run(&args, 6, 3, 100);
+        assert!(sampling.lost > 0);
+        assert_eq!(sampling.samples + sampling.lost, sampling.next_index);
+        assert!(sampling.samples == 100 || sampling.exhausted);
+    }
+}
@@ -0,0 +1,86 @@
+use std::{hash::Hasher, thread};
+
+use crate::stable_hash::StableHasher;
+
+/// Number of shards, fixed so that the contents of every shard do not depend on the thread count
+pub const SHARD_COUNT: usize = 64;
+
+/// Shard of a key. Uses a different hash than the Fx maps inside the shards,
+/// which would otherwise only see keys agreeing in some of their hash bits.
+pub fn shard_of(tour: &[u16]) -> usize {
+    let mut hasher = StableHasher::with_keys(0, 0);
+    hasher.write_tour(tour);
+    (hasher.finish() % SHARD_COUNT as u64) as usize
+}
+
+/// Collection split by key hash, so that disjoint shards can be updated by several threads at once
+pub struct Shards<T> {
+    shards: Vec<T>,
+}
+
+impl<T: Default + Send> Shards<T> {
+    pub fn new() -> Self {
+        Self::from_fn(T::default)
+    }
+}
+
+impl<T: Send> Shards<T> {
+    pub fn from_fn<F: Fn() -> T>(f: F) -> Self {
+        Shards {
+            shards: (0..SHARD_COUNT).map(|_| f()).collect(),
+        }
+    }
+
+    /// Shards restored from a checkpoint, in shard order
+    pub fn from_vec(shards: Vec<T>) -> Self {
+        assert_eq!(shards.len(), SHARD_COUNT, "Wrong number of shards");
+        Shards { shards }
+    }
+
+    pub fn get(&self, key: &[u16]) -> &T {
+        &self.shards[shard_of(key)]
+    }
+
+    pub fn get_mut(&mut self, key: &[u16]) -> &mut T {
+        &mut self.shards[shard_of(key)]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.shards.iter()
+    }
+
+    pub fn into_inner(self) -> Vec<T> {
+        self.shards
+    }
+
+    /// Calls `f` with the index and contents of every shard, spread over `threads` threads
+    pub fn for_each_parallel<R, F>(&mut self, threads: usize, f: F) -> Vec<R>
+    where
+        R: Send,
+        F: Fn(usize, &mut T) -> R + Sync,
+    {
+        let chunk_size = SHARD_COUNT.div_ceil(threads.max(1));
+        let f = &f;
+        thread::scope(|s| {
+            let handles: Vec<_> = self
+                .shards
+                .chunks_mut(chunk_size)
+                .enumerate()
+                .map(|(chunk, shards)| {
+                    s.spawn(move || {
+                        shards
+                            .iter_mut()
+                            .enumerate()
+                            .map(|(i, shard)| f(chunk * chunk_size + i, shard))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().expect("Shard thread panicked"))
+                .collect()
+        })
+    }
+}
@@ -16,12 +16,12 @@ pub fn parse_simple(path: &str) -> Result<Vec<Vec<i32>>, Box<dyn Error>> {
 
     let mut distance_matrix = vec![vec![0; n]; n];
 
-    for i in 0..n {
+    for row in distance_matrix.iter_mut() {
         let line = lines.next().ok_or(ParseError)?;
         let tokens: Vec<_> = line.split_whitespace().collect();
         for j in 0..n {
             let val: i32 = tokens[j].parse()?;
-            distance_matrix[i][j] = val;
+            row[j] = val;
         }
     }
 
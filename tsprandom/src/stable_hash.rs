@@ -0,0 +1,123 @@
+//! Hashes that are saved in checkpoints, so they must not change with the toolchain or the
+//! platform. `DefaultHasher` gives no such guarantee.
+
+use std::hash::Hasher;
+
+/// SipHash-1-3 with explicit keys, integers are hashed as little-endian bytes
+#[derive(Debug, Clone)]
+pub struct StableHasher {
+    v: [u64; 4],
+    tail: u64,
+    tail_len: usize,
+    len: usize,
+}
+
+impl StableHasher {
+    pub fn with_keys(k0: u64, k1: u64) -> Self {
+        StableHasher {
+            v: [
+                k0 ^ 0x736f6d6570736575,
+                k1 ^ 0x646f72616e646f6d,
+                k0 ^ 0x6c7967656e657261,
+                k1 ^ 0x7465646279746573,
+            ],
+            tail: 0,
+            tail_len: 0,
+            len: 0,
+        }
+    }
+
+    /// Hashes the number of cities and then every city
+    pub fn write_tour(&mut self, tour: &[u16]) {
+        self.write_usize(tour.len());
+        for &city in tour {
+            self.write_u16(city);
+        }
+    }
+
+    fn compress(&mut self, m: u64) {
+        self.v[3] ^= m;
+        sip_round(&mut self.v);
+        self.v[0] ^= m;
+    }
+}
+
+fn sip_round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13) ^ v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16) ^ v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21) ^ v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17) ^ v[2];
+    v[2] = v[2].rotate_left(32);
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.tail |= (byte as u64) << (8 * self.tail_len);
+            self.tail_len += 1;
+            if self.tail_len == 8 {
+                self.compress(self.tail);
+                self.tail = 0;
+                self.tail_len = 0;
+            }
+        }
+        self.len += bytes.len();
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hasher = self.clone();
+        let last = ((self.len as u64 & 0xff) << 56) | self.tail;
+        hasher.compress(last);
+        let v = &mut hasher.v;
+        v[2] ^= 0xff;
+        for _ in 0..3 {
+            sip_round(v);
+        }
+        v[0] ^ v[1] ^ v[2] ^ v[3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_are_fixed() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = StableHasher::with_keys(0, 0);
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        //reference values of SipHash-1-3 with zero keys
+        assert_eq!(hash(b""), 0xd1fba762150c532c);
+        assert_eq!(hash(b"tsprandom checkpoint"), 0x6aef116e48f2d690);
+
+        let mut hasher = StableHasher::with_keys(0, 0);
+        hasher.write_tour(&[0, 2, 1]);
+        let mut bytes = vec![];
+        bytes.extend(3u64.to_le_bytes());
+        bytes.extend([0, 0, 2, 0, 1, 0]);
+        assert_eq!(hasher.finish(), hash(&bytes));
+    }
+}
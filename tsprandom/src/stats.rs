@@ -0,0 +1,262 @@
+use std::fmt::Display;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Running mean, variances and covariance of paired samples (Welford's algorithm)
+#[derive(Debug, Clone, Default)]
+pub struct Welford {
+    n: usize,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    c: f64,
+}
+
+impl Welford {
+    pub fn push(&mut self, x: f64, y: f64) {
+        self.n += 1;
+        let dx = x - self.mean_x;
+        self.mean_x += dx / self.n as f64;
+        let dy = y - self.mean_y;
+        self.mean_y += dy / self.n as f64;
+        self.m2_x += dx * (x - self.mean_x);
+        self.m2_y += dy * (y - self.mean_y);
+        self.c += dx * (y - self.mean_y);
+    }
+
+    pub fn count(&self) -> usize {
+        self.n
+    }
+
+    pub fn mean_x(&self) -> f64 {
+        self.mean_x
+    }
+
+    pub fn mean_y(&self) -> f64 {
+        self.mean_y
+    }
+
+    /// Sample standard deviation of x, needs at least two samples
+    pub fn std_dev_x(&self) -> Option<f64> {
+        (self.n > 1).then(|| (self.m2_x / (self.n - 1) as f64).sqrt())
+    }
+
+    pub fn std_dev_y(&self) -> Option<f64> {
+        (self.n > 1).then(|| (self.m2_y / (self.n - 1) as f64).sqrt())
+    }
+
+    pub fn covariance(&self) -> Option<f64> {
+        (self.n > 1).then(|| self.c / (self.n - 1) as f64)
+    }
+
+    /// Pearson correlation, undefined for fewer than two samples or a constant variable
+    pub fn correlation(&self) -> Option<f64> {
+        if self.n < 2 || self.m2_x <= 0.0 || self.m2_y <= 0.0 {
+            return None;
+        }
+        Some(self.c / (self.m2_x * self.m2_y).sqrt())
+    }
+}
+
+pub fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let mut welford = Welford::default();
+    for (&x, &y) in xs.iter().zip(ys) {
+        welford.push(x, y);
+    }
+    welford.correlation()
+}
+
+/// Ranks starting at 1, ties get the average of their ranks
+pub fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        for &k in &order[i..=j] {
+            ranks[k] = rank;
+        }
+        i = j + 1;
+    }
+    ranks
+}
+
+pub fn spearman(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    pearson(&ranks(xs), &ranks(ys))
+}
+
+/// Percentile bootstrap confidence interval of a statistic of paired samples.
+/// Resamples on which the statistic is undefined are skipped.
+pub fn bootstrap_ci<F: Fn(&[f64], &[f64]) -> Option<f64>>(
+    xs: &[f64],
+    ys: &[f64],
+    statistic: F,
+    resamples: usize,
+    confidence: f64,
+    seed: Option<u64>,
+) -> Option<(f64, f64)> {
+    let n = xs.len();
+    if n < 2 || resamples == 0 {
+        return None;
+    }
+    let mut rng = match seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+
+    let mut values = vec![];
+    let (mut sample_x, mut sample_y) = (vec![0.0; n], vec![0.0; n]);
+    for _ in 0..resamples {
+        for k in 0..n {
+            let i = rng.gen_range(0..n);
+            sample_x[k] = xs[i];
+            sample_y[k] = ys[i];
+        }
+        if let Some(value) = statistic(&sample_x, &sample_y) {
+            values.push(value);
+        }
+    }
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_by(f64::total_cmp);
+    let alpha = (1.0 - confidence) / 2.0;
+    let at =
+        |q: f64| values[((q * (values.len() - 1) as f64).round() as usize).min(values.len() - 1)];
+    Some((at(alpha), at(1.0 - alpha)))
+}
+
+/// Fitness distance correlation between the distance of each local optimum to the best one
+/// and its tour length difference to the best one
+#[derive(Debug, Clone)]
+pub struct FdcResult {
+    pub samples: usize,
+    pub mean_distance: Option<f64>,
+    pub mean_height_diff: Option<f64>,
+    pub std_distance: Option<f64>,
+    pub std_height_diff: Option<f64>,
+    pub covariance: Option<f64>,
+    pub pearson: Option<f64>,
+    pub pearson_ci: Option<(f64, f64)>,
+    pub spearman: Option<f64>,
+    pub spearman_ci: Option<(f64, f64)>,
+    pub confidence: f64,
+}
+
+pub fn fitness_distance_correlation(
+    distances: &[f64],
+    height_diff: &[f64],
+    resamples: usize,
+    seed: Option<u64>,
+) -> FdcResult {
+    let mut welford = Welford::default();
+    for (&d, &h) in distances.iter().zip(height_diff) {
+        welford.push(d, h);
+    }
+    let has_samples = welford.count() > 0;
+    let confidence = 0.95;
+
+    FdcResult {
+        samples: welford.count(),
+        mean_distance: has_samples.then(|| welford.mean_x()),
+        mean_height_diff: has_samples.then(|| welford.mean_y()),
+        std_distance: welford.std_dev_x(),
+        std_height_diff: welford.std_dev_y(),
+        covariance: welford.covariance(),
+        pearson: welford.correlation(),
+        pearson_ci: bootstrap_ci(distances, height_diff, pearson, resamples, confidence, seed),
+        spearman: spearman(distances, height_diff),
+        spearman_ci: bootstrap_ci(
+            distances,
+            height_diff,
+            spearman,
+            resamples,
+            confidence,
+            seed,
+        ),
+        confidence,
+    }
+}
+
+fn fmt_opt(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.4}", value),
+        None => String::from("n/a"),
+    }
+}
+
+fn fmt_ci(ci: Option<(f64, f64)>) -> String {
+    match ci {
+        Some((low, high)) => format!("[{:.4}, {:.4}]", low, high),
+        None => String::from("n/a"),
+    }
+}
+
+impl Display for FdcResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = (self.confidence * 100.0).round();
+        writeln!(f, "Local optima compared to the best: {}", self.samples)?;
+        writeln!(
+            f,
+            "Mean distance: {}, Mean height difference: {}",
+            fmt_opt(self.mean_distance),
+            fmt_opt(self.mean_height_diff)
+        )?;
+        writeln!(
+            f,
+            "std dev. of distance: {}, std dev. of height diff: {}",
+            fmt_opt(self.std_distance),
+            fmt_opt(self.std_height_diff)
+        )?;
+        writeln!(f, "Covariance: {}", fmt_opt(self.covariance))?;
+        writeln!(
+            f,
+            "Pearson correlation (FDC): {} ({}% CI {})",
+            fmt_opt(self.pearson),
+            level,
+            fmt_ci(self.pearson_ci)
+        )?;
+        write!(
+            f,
+            "Spearman correlation: {} ({}% CI {})",
+            fmt_opt(self.spearman),
+            level,
+            fmt_ci(self.spearman_ci)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn correlations_of_monotone_data() {
+        let xs = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let ys = [1.0, 4.0, 9.0, 16.0, 100.0];
+
+        assert_eq!(spearman(&xs, &ys), Some(1.0));
+        assert!(pearson(&xs, &ys).unwrap() < 1.0);
+        assert_eq!(ranks(&[3.0, 1.0, 3.0]), vec![2.5, 1.0, 2.5]);
+    }
+
+    #[test]
+    fn degenerate_samples_have_no_correlation() {
+        let result = fitness_distance_correlation(&[], &[], 100, Some(1));
+        assert_eq!(result.samples, 0);
+        assert!(result.mean_distance.is_none() && result.pearson.is_none());
+
+        let result = fitness_distance_correlation(&[2.0, 2.0], &[1.0, 5.0], 100, Some(1));
+        assert_eq!(result.std_distance, Some(0.0));
+        assert!(result.pearson.is_none() && result.spearman.is_none());
+    }
+}
@@ -0,0 +1,20 @@
+/// Irregular but symmetric distances `|i - j| * (i + j + 1)`, taken modulo `modulus` if given
+/// to get more local optima
+pub fn skewed_matrix(n: usize, modulus: Option<i32>) -> Vec<Vec<i32>> {
+    (0..n as i32)
+        .map(|i| {
+            (0..n as i32)
+                .map(|j| {
+                    let d = (i - j).abs() * (i + j + 1);
+                    modulus.map_or(d, |m| d % m)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn is_permutation(tour: &[u16], n: usize) -> bool {
+    let mut sorted = tour.to_vec();
+    sorted.sort_unstable();
+    sorted == (0..n as u16).collect::<Vec<u16>>()
+}
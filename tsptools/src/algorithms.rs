@@ -1,2 +1,7 @@
+pub mod aco;
+pub mod genetic;
+pub mod grasp;
+pub mod guided_local_search;
 pub mod hillclimb;
 pub mod two_opt;
+pub mod vns;
@@ -0,0 +1,552 @@
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::{self, available_parallelism},
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    algorithms::two_opt::two_opt,
+    helpers::{candidate_lists, is_symmetric, nearest_neighbor_tour, tour_len},
+    solver::{
+        parse_checked, parse_param, Budget, CancelToken, ConfigError, Improvement, Solution,
+        Solver, Tracker,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcoVariant {
+    AntSystem,
+    MaxMin,
+    AntColonySystem,
+}
+
+#[derive(Debug, Clone)]
+pub struct AcoConfig {
+    pub variant: AcoVariant,
+    pub ants: usize,
+    pub iterations: usize,
+    /// Pheromone influence
+    pub alpha: f64,
+    /// Heuristic (1 / distance) influence
+    pub beta: f64,
+    /// Pheromone evaporation rate
+    pub rho: f64,
+    /// ACS only: probability of greedily taking the best candidate
+    pub q0: f64,
+    /// ACS only: local pheromone update rate
+    pub xi: f64,
+    /// Number of nearest neighbors considered when building tours
+    pub candidates: usize,
+    /// Improve every constructed tour with 2-opt (daemon action)
+    pub local_search: bool,
+    /// Number of construction threads, 0 uses all available cores
+    pub threads: usize,
+    pub preserve_first: bool,
+    pub seed: Option<u64>,
+}
+
+impl Default for AcoConfig {
+    fn default() -> Self {
+        Self {
+            variant: AcoVariant::MaxMin,
+            ants: 25,
+            iterations: 500,
+            alpha: 1.0,
+            beta: 2.0,
+            rho: 0.02,
+            q0: 0.9,
+            xi: 0.1,
+            candidates: 20,
+            local_search: false,
+            threads: 0,
+            preserve_first: false,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IterationStats {
+    pub iteration: usize,
+    pub best_len: i32,
+    pub mean_len: f64,
+    pub worst_len: i32,
+    pub global_best_len: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AcoResult {
+    pub tour: Vec<u16>,
+    pub len: i32,
+    pub stats: Vec<IterationStats>,
+}
+
+/// Panics if `q0` is not in [0, 1] or `rho` is not in (0, 1]
+pub fn ant_colony(distance_matrix: &[Vec<i32>], config: &AcoConfig) -> AcoResult {
+    ant_colony_tracked(distance_matrix, config, None, &mut Tracker::unlimited())
+}
+
+fn ant_colony_tracked(
+    distance_matrix: &[Vec<i32>],
+    config: &AcoConfig,
+    initial: Option<&[u16]>,
+    tracker: &mut Tracker,
+) -> AcoResult {
+    assert!(
+        (0.0..=1.0).contains(&config.q0),
+        "q0 must be in [0, 1], got {}",
+        config.q0
+    );
+    assert!(
+        config.rho > 0.0 && config.rho <= 1.0,
+        "rho must be in (0, 1], got {}",
+        config.rho
+    );
+    let n = distance_matrix.len();
+    let base_seed = config
+        .seed
+        .unwrap_or_else(|| ChaCha8Rng::from_entropy().gen());
+    let symmetric = is_symmetric(distance_matrix);
+    let candidates = candidate_lists(distance_matrix, config.candidates.max(1));
+    let threads = match config.threads {
+        0 => available_parallelism().map(|n| n.get()).unwrap_or(1),
+        t => t,
+    };
+
+    let nn_tour = nearest_neighbor_tour(distance_matrix, 0);
+    let nn_len = tour_len(&nn_tour, distance_matrix);
+
+    let mut best_tour = nn_tour;
+    let mut best_len = nn_len;
+    if let Some(tour) = initial {
+        let len = tour_len(tour, distance_matrix);
+        if len < best_len {
+            best_tour = tour.to_vec();
+            best_len = len;
+        }
+    }
+    let mut stats = vec![];
+    tracker.evaluated(1 + initial.is_some() as u64);
+    tracker.improved(best_len);
+
+    if n < 4 {
+        return finish(best_tour, best_len, stats, config.preserve_first);
+    }
+
+    let nn_len = nn_len.max(1) as f64;
+    let tau_0 = match config.variant {
+        AcoVariant::AntSystem => config.ants as f64 / nn_len,
+        AcoVariant::MaxMin => 1.0 / (config.rho * nn_len),
+        AcoVariant::AntColonySystem => 1.0 / (n as f64 * nn_len),
+    };
+    let mut pheromone = vec![vec![tau_0; n]; n];
+
+    let heuristic: Vec<Vec<f64>> = distance_matrix
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&d| (1.0 / (d as f64 + 0.1)).powf(config.beta))
+                .collect()
+        })
+        .collect();
+
+    for iteration in 0..config.iterations {
+        if tracker.should_stop() {
+            break;
+        }
+
+        let choice_info: Vec<Vec<f64>> = pheromone
+            .iter()
+            .zip(&heuristic)
+            .map(|(tau, eta)| {
+                tau.iter()
+                    .zip(eta)
+                    .map(|(t, e)| t.powf(config.alpha) * e)
+                    .collect()
+            })
+            .collect();
+
+        let ants = construct_ants(
+            distance_matrix,
+            &choice_info,
+            &candidates,
+            config,
+            threads,
+            base_seed,
+            iteration,
+        );
+
+        let (iter_best, iter_best_len) = ants
+            .iter()
+            .min_by_key(|(_, len)| *len)
+            .map(|(tour, len)| (tour.clone(), *len))
+            .unwrap();
+        if iter_best_len < best_len {
+            best_len = iter_best_len;
+            best_tour = iter_best.clone();
+        }
+        tracker.iteration();
+        tracker.evaluated(ants.len() as u64);
+        tracker.improved(best_len);
+
+        stats.push(IterationStats {
+            iteration,
+            best_len: iter_best_len,
+            mean_len: ants.iter().map(|(_, len)| *len as f64).sum::<f64>() / ants.len() as f64,
+            worst_len: ants.iter().map(|(_, len)| *len).max().unwrap(),
+            global_best_len: best_len,
+        });
+
+        let deposit = |pheromone: &mut Vec<Vec<f64>>, tour: &[u16], amount: f64| {
+            for_each_edge(tour, |a, b| {
+                pheromone[a][b] += amount;
+                if symmetric {
+                    pheromone[b][a] += amount;
+                }
+            });
+        };
+
+        match config.variant {
+            AcoVariant::AntSystem => {
+                evaporate(&mut pheromone, config.rho);
+                for (tour, len) in &ants {
+                    deposit(&mut pheromone, tour, 1.0 / (*len).max(1) as f64);
+                }
+            }
+            AcoVariant::MaxMin => {
+                evaporate(&mut pheromone, config.rho);
+                //every few iterations reinforce the best-so-far tour instead of the iteration best
+                let (tour, len) = match iteration % 5 == 4 {
+                    true => (&best_tour, best_len),
+                    false => (&iter_best, iter_best_len),
+                };
+                deposit(&mut pheromone, tour, 1.0 / len.max(1) as f64);
+
+                let tau_max = 1.0 / (config.rho * best_len.max(1) as f64);
+                let tau_min = mmas_tau_min(tau_max, n);
+                for row in pheromone.iter_mut() {
+                    for tau in row.iter_mut() {
+                        *tau = tau.clamp(tau_min, tau_max);
+                    }
+                }
+            }
+            AcoVariant::AntColonySystem => {
+                //local updates are applied once all ants have been constructed in parallel
+                for (tour, _) in &ants {
+                    for_each_edge(tour, |a, b| {
+                        pheromone[a][b] = (1.0 - config.xi) * pheromone[a][b] + config.xi * tau_0;
+                        if symmetric {
+                            pheromone[b][a] = pheromone[a][b];
+                        }
+                    });
+                }
+                let amount = config.rho / best_len.max(1) as f64;
+                for_each_edge(&best_tour, |a, b| {
+                    pheromone[a][b] = (1.0 - config.rho) * pheromone[a][b] + amount;
+                    if symmetric {
+                        pheromone[b][a] = pheromone[a][b];
+                    }
+                });
+            }
+        }
+    }
+
+    finish(best_tour, best_len, stats, config.preserve_first)
+}
+
+fn finish(
+    mut tour: Vec<u16>,
+    len: i32,
+    stats: Vec<IterationStats>,
+    preserve_first: bool,
+) -> AcoResult {
+    if preserve_first {
+        if let Some(pos) = tour.iter().position(|&c| c == 0) {
+            tour.rotate_left(pos);
+        }
+    }
+    AcoResult { tour, len, stats }
+}
+
+fn mmas_tau_min(tau_max: f64, n: usize) -> f64 {
+    let p_root = 0.05f64.powf(1.0 / n as f64);
+    tau_max * (1.0 - p_root) / ((n as f64 / 2.0 - 1.0).max(1.0) * p_root)
+}
+
+fn evaporate(pheromone: &mut [Vec<f64>], rho: f64) {
+    for row in pheromone.iter_mut() {
+        for tau in row.iter_mut() {
+            *tau *= 1.0 - rho;
+        }
+    }
+}
+
+fn for_each_edge<F: FnMut(usize, usize)>(tour: &[u16], mut f: F) {
+    for i in 0..tour.len() {
+        f(tour[i] as usize, tour[(i + 1) % tour.len()] as usize);
+    }
+}
+
+fn construct_ants(
+    distance_matrix: &[Vec<i32>],
+    choice_info: &[Vec<f64>],
+    candidates: &[Vec<u16>],
+    config: &AcoConfig,
+    threads: usize,
+    base_seed: u64,
+    iteration: usize,
+) -> Vec<(Vec<u16>, i32)> {
+    let next_ant = AtomicUsize::new(0);
+
+    let mut ants: Vec<(usize, Vec<u16>, i32)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads.min(config.ants.max(1)))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut built = vec![];
+                    loop {
+                        let ant = next_ant.fetch_add(1, Ordering::Relaxed);
+                        if ant >= config.ants.max(1) {
+                            break;
+                        }
+                        //every ant has its own stream so results do not depend on thread count
+                        let mut rng = ChaCha8Rng::seed_from_u64(base_seed);
+                        rng.set_stream((iteration * config.ants.max(1) + ant) as u64);
+
+                        let tour = construct_tour(choice_info, candidates, config, &mut rng);
+                        let (tour, len) = match config.local_search {
+                            true => two_opt(&tour, distance_matrix, false),
+                            false => {
+                                let len = tour_len(&tour, distance_matrix);
+                                (tour, len)
+                            }
+                        };
+                        built.push((ant, tour, len));
+                    }
+                    built
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("Ant construction thread panicked"))
+            .collect()
+    });
+
+    ants.sort_by_key(|(ant, _, _)| *ant);
+    ants.into_iter().map(|(_, tour, len)| (tour, len)).collect()
+}
+
+fn construct_tour<R: Rng>(
+    choice_info: &[Vec<f64>],
+    candidates: &[Vec<u16>],
+    config: &AcoConfig,
+    rng: &mut R,
+) -> Vec<u16> {
+    let n = choice_info.len();
+    let mut visited = vec![false; n];
+    let mut tour = Vec::with_capacity(n);
+
+    let mut current = rng.gen_range(0..n);
+    visited[current] = true;
+    tour.push(current as u16);
+
+    while tour.len() < n {
+        let open: Vec<usize> = candidates[current]
+            .iter()
+            .map(|&c| c as usize)
+            .filter(|&c| !visited[c])
+            .collect();
+
+        let greedy = config.variant == AcoVariant::AntColonySystem && rng.gen_bool(config.q0);
+        current = if open.is_empty() {
+            //all candidates used up, take the best of the remaining cities
+            (0..n)
+                .filter(|&c| !visited[c])
+                .max_by(|&a, &b| choice_info[current][a].total_cmp(&choice_info[current][b]))
+                .unwrap()
+        } else if greedy {
+            *open
+                .iter()
+                .max_by(|&&a, &&b| choice_info[current][a].total_cmp(&choice_info[current][b]))
+                .unwrap()
+        } else {
+            roulette(&open, &choice_info[current], rng)
+        };
+
+        visited[current] = true;
+        tour.push(current as u16);
+    }
+
+    tour
+}
+
+fn roulette<R: Rng>(open: &[usize], weights: &[f64], rng: &mut R) -> usize {
+    let total: f64 = open.iter().map(|&c| weights[c]).sum();
+    if total <= 0.0 || !total.is_finite() {
+        return open[rng.gen_range(0..open.len())];
+    }
+
+    let mut pick = rng.gen_range(0.0..total);
+    for &c in open {
+        pick -= weights[c];
+        if pick <= 0.0 {
+            return c;
+        }
+    }
+    *open.last().unwrap()
+}
+
+impl FromStr for AcoVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "as" => Ok(Self::AntSystem),
+            "mmas" => Ok(Self::MaxMin),
+            "acs" => Ok(Self::AntColonySystem),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AntColony {
+    pub config: AcoConfig,
+    /// Per-iteration statistics of the last run
+    pub stats: Vec<IterationStats>,
+}
+
+impl Solver for AntColony {
+    fn name(&self) -> &'static str {
+        "aco"
+    }
+
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError> {
+        let config = &mut self.config;
+        match parameter {
+            "variant" => config.variant = parse_param(parameter, value)?,
+            "ants" => config.ants = parse_param(parameter, value)?,
+            "iterations" => config.iterations = parse_param(parameter, value)?,
+            "alpha" => config.alpha = parse_param(parameter, value)?,
+            "beta" => config.beta = parse_param(parameter, value)?,
+            "rho" => config.rho = parse_checked(parameter, value, |rho| rho > 0.0 && rho <= 1.0)?,
+            "q0" => config.q0 = parse_checked(parameter, value, |q0| (0.0..=1.0).contains(&q0))?,
+            "xi" => config.xi = parse_param(parameter, value)?,
+            "candidates" => config.candidates = parse_param(parameter, value)?,
+            "local_search" => config.local_search = parse_param(parameter, value)?,
+            "threads" => config.threads = parse_param(parameter, value)?,
+            "preserve_first" => config.preserve_first = parse_param(parameter, value)?,
+            "seed" => config.seed = Some(parse_param(parameter, value)?),
+            _ => return Err(ConfigError::UnknownParameter(parameter.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution {
+        let mut tracker = Tracker::new(budget, cancel, on_improvement);
+        let result = ant_colony_tracked(distance_matrix, &self.config, initial, &mut tracker);
+        self.stats = result.stats;
+        tracker.solution(result.tour, result.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::circle_matrix;
+
+    #[test]
+    fn variants_find_circle_optimum() {
+        let matrix = circle_matrix(12);
+        let optimum = tour_len(&(0..12).collect::<Vec<u16>>(), &matrix);
+
+        for variant in [
+            AcoVariant::AntSystem,
+            AcoVariant::MaxMin,
+            AcoVariant::AntColonySystem,
+        ] {
+            let config = AcoConfig {
+                variant,
+                ants: 10,
+                iterations: 100,
+                threads: 2,
+                preserve_first: true,
+                seed: Some(3),
+                ..Default::default()
+            };
+            let result = ant_colony(&matrix, &config);
+            assert_eq!(result.tour[0], 0);
+            assert_eq!(result.len, optimum, "{:?}", variant);
+            assert_eq!(result.stats.len(), 100);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_parameters_and_keeps_nn_length() {
+        let mut solver = AntColony::default();
+        for (parameter, value) in [("q0", "1.5"), ("q0", "-0.1"), ("rho", "0"), ("rho", "2")] {
+            assert!(
+                solver.configure(parameter, value).is_err(),
+                "{}={}",
+                parameter,
+                value
+            );
+        }
+        assert!(solver.configure("q0", "1").is_ok());
+        assert!(solver.configure("rho", "1").is_ok());
+
+        let result = ant_colony(&[vec![0]], &AcoConfig::default());
+        assert_eq!((result.tour, result.len), (vec![0], 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "rho must be in (0, 1]")]
+    fn rejects_invalid_config() {
+        let config = AcoConfig {
+            rho: 0.0,
+            ..Default::default()
+        };
+        ant_colony(&circle_matrix(5), &config);
+    }
+
+    #[test]
+    fn results_do_not_depend_on_thread_count() {
+        let matrix = circle_matrix(15);
+        let config = AcoConfig {
+            iterations: 20,
+            seed: Some(11),
+            ..Default::default()
+        };
+
+        let single = ant_colony(
+            &matrix,
+            &AcoConfig {
+                threads: 1,
+                ..config.clone()
+            },
+        );
+        let multi = ant_colony(
+            &matrix,
+            &AcoConfig {
+                threads: 4,
+                ..config
+            },
+        );
+        assert_eq!(single.tour, multi.tour);
+        assert_eq!(
+            single.stats.last().unwrap().mean_len,
+            multi.stats.last().unwrap().mean_len
+        );
+    }
+}
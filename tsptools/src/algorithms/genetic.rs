@@ -0,0 +1,734 @@
+use std::str::FromStr;
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    algorithms::two_opt::two_opt,
+    helpers::{random_solution, tour_len},
+    solver::{
+        parse_param, Budget, CancelToken, ConfigError, Improvement, Solution, Solver, Tracker,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// Best of `k` individuals drawn uniformly at random
+    Tournament(usize),
+    /// Fitness proportionate selection with fitness 1 / (tour length + 1)
+    Roulette,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crossover {
+    /// Order crossover
+    Ox,
+    /// Partially mapped crossover
+    Pmx,
+    /// Cycle crossover
+    Cx,
+    /// Edge recombination crossover
+    Erx,
+    /// Edge assembly crossover (single AB-cycle strategy)
+    Eax,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutation {
+    Swap,
+    Inversion,
+    Scramble,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneticConfig {
+    pub population_size: usize,
+    pub generations: usize,
+    pub selection: Selection,
+    pub crossover: Crossover,
+    pub crossover_rate: f64,
+    pub mutation: Mutation,
+    pub mutation_rate: f64,
+    /// Number of best individuals copied unchanged to the next generation
+    pub elitism: usize,
+    /// Apply 2-opt to every offspring (memetic algorithm)
+    pub memetic: bool,
+    pub preserve_first: bool,
+    pub seed: Option<u64>,
+}
+
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        Self {
+            population_size: 100,
+            generations: 500,
+            selection: Selection::Tournament(3),
+            crossover: Crossover::Ox,
+            crossover_rate: 0.9,
+            mutation: Mutation::Inversion,
+            mutation_rate: 0.1,
+            elitism: 2,
+            memetic: false,
+            preserve_first: false,
+            seed: None,
+        }
+    }
+}
+
+pub fn genetic(distance_matrix: &[Vec<i32>], config: &GeneticConfig) -> (Vec<u16>, i32) {
+    genetic_tracked(distance_matrix, config, None, &mut Tracker::unlimited())
+}
+
+fn genetic_tracked(
+    distance_matrix: &[Vec<i32>],
+    config: &GeneticConfig,
+    initial: Option<&[u16]>,
+    tracker: &mut Tracker,
+) -> (Vec<u16>, i32) {
+    let n = distance_matrix.len();
+    let mut rng = match config.seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+
+    let mut population: Vec<(Vec<u16>, i32)> = (0..config.population_size.max(1))
+        .map(|i| {
+            let tour = match (i, initial) {
+                (0, Some(tour)) => tour.to_vec(),
+                _ => random_solution(n as u16, Some(rng.gen()), config.preserve_first),
+            };
+            evaluate(tour, distance_matrix, config)
+        })
+        .collect();
+    population.sort_by_key(|(_, len)| *len);
+    tracker.evaluated(population.len() as u64);
+    tracker.improved(population[0].1);
+
+    if n < 4 {
+        return population.swap_remove(0);
+    }
+
+    for _ in 0..config.generations {
+        if tracker.should_stop() {
+            break;
+        }
+
+        let mut next: Vec<(Vec<u16>, i32)> = population
+            .iter()
+            .take(config.elitism.min(population.len()))
+            .cloned()
+            .collect();
+        let elite = next.len();
+
+        while next.len() < population.len() {
+            let p1 = &population[select(&population, config.selection, &mut rng)].0;
+            let p2 = &population[select(&population, config.selection, &mut rng)].0;
+
+            let mut child = if rng.gen_bool(config.crossover_rate.clamp(0.0, 1.0)) {
+                config.crossover.apply(p1, p2, distance_matrix, &mut rng)
+            } else {
+                p1.clone()
+            };
+
+            if rng.gen_bool(config.mutation_rate.clamp(0.0, 1.0)) {
+                config
+                    .mutation
+                    .apply(&mut child, config.preserve_first, &mut rng);
+            }
+
+            next.push(evaluate(child, distance_matrix, config));
+        }
+
+        next.sort_by_key(|(_, len)| *len);
+        population = next;
+
+        tracker.iteration();
+        tracker.evaluated((population.len() - elite) as u64);
+        tracker.improved(population[0].1);
+    }
+
+    population.swap_remove(0)
+}
+
+fn evaluate(
+    mut tour: Vec<u16>,
+    distance_matrix: &[Vec<i32>],
+    config: &GeneticConfig,
+) -> (Vec<u16>, i32) {
+    if config.preserve_first {
+        rotate_to_first(&mut tour);
+    }
+    if config.memetic && tour.len() > 2 {
+        return two_opt(&tour, distance_matrix, config.preserve_first);
+    }
+    let len = tour_len(&tour, distance_matrix);
+    (tour, len)
+}
+
+fn rotate_to_first(tour: &mut [u16]) {
+    if let Some(pos) = tour.iter().position(|&c| c == 0) {
+        tour.rotate_left(pos);
+    }
+}
+
+fn select<R: Rng>(population: &[(Vec<u16>, i32)], selection: Selection, rng: &mut R) -> usize {
+    match selection {
+        Selection::Tournament(k) => (0..k.max(1))
+            .map(|_| rng.gen_range(0..population.len()))
+            .min_by_key(|&i| population[i].1)
+            .unwrap(),
+        Selection::Roulette => {
+            let fitness = |len: i32| 1.0 / (len.max(0) as f64 + 1.0);
+            let total: f64 = population.iter().map(|(_, len)| fitness(*len)).sum();
+            let mut pick = rng.gen_range(0.0..total);
+            for (i, (_, len)) in population.iter().enumerate() {
+                pick -= fitness(*len);
+                if pick <= 0.0 {
+                    return i;
+                }
+            }
+            population.len() - 1
+        }
+    }
+}
+
+impl Crossover {
+    pub fn apply<R: Rng>(
+        &self,
+        p1: &[u16],
+        p2: &[u16],
+        distance_matrix: &[Vec<i32>],
+        rng: &mut R,
+    ) -> Vec<u16> {
+        if p1.len() < 2 {
+            return p1.to_vec();
+        }
+
+        match self {
+            Self::Ox => order_crossover(p1, p2, rng),
+            Self::Pmx => partially_mapped_crossover(p1, p2, rng),
+            Self::Cx => cycle_crossover(p1, p2),
+            Self::Erx => edge_recombination(p1, p2, rng),
+            Self::Eax => edge_assembly_crossover(p1, p2, distance_matrix, rng),
+        }
+    }
+}
+
+impl Mutation {
+    pub fn apply<R: Rng>(&self, tour: &mut [u16], preserve_first: bool, rng: &mut R) {
+        let start = match preserve_first {
+            true => 1,
+            false => 0,
+        };
+        if tour.len() < start + 2 {
+            return;
+        }
+
+        let (a, b) = random_segment(start, tour.len(), rng);
+        match self {
+            Self::Swap => tour.swap(a, b),
+            Self::Inversion => tour[a..=b].reverse(),
+            Self::Scramble => tour[a..=b].shuffle(rng),
+        }
+    }
+}
+
+/// Returns two distinct positions a < b from range [start, end)
+fn random_segment<R: Rng>(start: usize, end: usize, rng: &mut R) -> (usize, usize) {
+    let a = rng.gen_range(start..end);
+    let mut b = rng.gen_range(start..end - 1);
+    if b >= a {
+        b += 1;
+    }
+    (a.min(b), a.max(b))
+}
+
+pub fn order_crossover<R: Rng>(p1: &[u16], p2: &[u16], rng: &mut R) -> Vec<u16> {
+    let n = p1.len();
+    let (a, b) = random_segment(0, n, rng);
+
+    let mut used = vec![false; n];
+    let mut child = vec![0; n];
+    for i in a..=b {
+        child[i] = p1[i];
+        used[p1[i] as usize] = true;
+    }
+
+    //fill remaining positions in order of p2, starting after the copied segment
+    let mut pos = (b + 1) % n;
+    for k in 0..n {
+        let city = p2[(b + 1 + k) % n];
+        if !used[city as usize] {
+            child[pos] = city;
+            pos = (pos + 1) % n;
+        }
+    }
+
+    child
+}
+
+pub fn partially_mapped_crossover<R: Rng>(p1: &[u16], p2: &[u16], rng: &mut R) -> Vec<u16> {
+    let n = p1.len();
+    let (a, b) = random_segment(0, n, rng);
+
+    let mut pos_in_p1 = vec![0; n];
+    for (i, &city) in p1.iter().enumerate() {
+        pos_in_p1[city as usize] = i;
+    }
+
+    let in_segment = |i: usize| i >= a && i <= b;
+    let mut child = p2.to_vec();
+    child[a..=b].copy_from_slice(&p1[a..=b]);
+
+    for i in (0..n).filter(|&i| !in_segment(i)) {
+        //follow the mapping until we leave the copied segment
+        let mut city = p2[i];
+        while in_segment(pos_in_p1[city as usize]) {
+            city = p2[pos_in_p1[city as usize]];
+        }
+        child[i] = city;
+    }
+
+    child
+}
+
+pub fn cycle_crossover(p1: &[u16], p2: &[u16]) -> Vec<u16> {
+    let n = p1.len();
+    let mut pos_in_p1 = vec![0; n];
+    for (i, &city) in p1.iter().enumerate() {
+        pos_in_p1[city as usize] = i;
+    }
+
+    let mut child = vec![0; n];
+    let mut assigned = vec![false; n];
+    let mut from_first = true;
+
+    for start in 0..n {
+        if assigned[start] {
+            continue;
+        }
+        let mut i = start;
+        while !assigned[i] {
+            child[i] = if from_first { p1[i] } else { p2[i] };
+            assigned[i] = true;
+            i = pos_in_p1[p2[i] as usize];
+        }
+        from_first = !from_first;
+    }
+
+    child
+}
+
+pub fn edge_recombination<R: Rng>(p1: &[u16], p2: &[u16], rng: &mut R) -> Vec<u16> {
+    let n = p1.len();
+    let mut adjacency: Vec<Vec<u16>> = vec![Vec::with_capacity(4); n];
+    for parent in [p1, p2] {
+        for i in 0..n {
+            let city = parent[i] as usize;
+            for neighbor in [parent[(i + n - 1) % n], parent[(i + 1) % n]] {
+                if !adjacency[city].contains(&neighbor) {
+                    adjacency[city].push(neighbor);
+                }
+            }
+        }
+    }
+
+    //unvisited cities, removed by swap-remove through their index in the pool
+    let mut unvisited: Vec<u16> = (0..n as u16).collect();
+    let mut pool_index: Vec<usize> = (0..n).collect();
+    let mut child = Vec::with_capacity(n);
+    let mut current = p1[0];
+
+    loop {
+        child.push(current);
+        let index = pool_index[current as usize];
+        unvisited.swap_remove(index);
+        if let Some(&moved) = unvisited.get(index) {
+            pool_index[moved as usize] = index;
+        }
+        if child.len() == n {
+            break;
+        }
+        //edges are undirected, so only the neighbors of current list it
+        for k in 0..adjacency[current as usize].len() {
+            let neighbor = adjacency[current as usize][k] as usize;
+            adjacency[neighbor].retain(|&c| c != current);
+        }
+
+        let candidates = &adjacency[current as usize];
+        current = if candidates.is_empty() {
+            *unvisited.choose(rng).unwrap()
+        } else {
+            //prefer the neighbor with the fewest remaining edges, ties broken randomly
+            let fewest = candidates
+                .iter()
+                .map(|&c| adjacency[c as usize].len())
+                .min()
+                .unwrap();
+            let best: Vec<u16> = candidates
+                .iter()
+                .copied()
+                .filter(|&c| adjacency[c as usize].len() == fewest)
+                .collect();
+            *best.choose(rng).unwrap()
+        };
+    }
+
+    child
+}
+
+pub fn edge_assembly_crossover<R: Rng>(
+    p1: &[u16],
+    p2: &[u16],
+    distance_matrix: &[Vec<i32>],
+    rng: &mut R,
+) -> Vec<u16> {
+    let n = p1.len();
+    let adj_a = adjacency(p1);
+    let adj_b = adjacency(p2);
+
+    //edges present in only one of the parents
+    let mut rem_a: Vec<Vec<u16>> = (0..n)
+        .map(|v| {
+            let b = adj_b[v];
+            adj_a[v]
+                .iter()
+                .copied()
+                .filter(|c| !b.contains(c))
+                .collect()
+        })
+        .collect();
+    let mut rem_b: Vec<Vec<u16>> = (0..n)
+        .map(|v| {
+            let a = adj_a[v];
+            adj_b[v]
+                .iter()
+                .copied()
+                .filter(|c| !a.contains(c))
+                .collect()
+        })
+        .collect();
+
+    let cycles = ab_cycles(&mut rem_a, &mut rem_b, rng);
+    let cycle = match cycles.choose(rng) {
+        Some(cycle) => cycle,
+        None => return p1.to_vec(),
+    };
+
+    //replace A-edges of the chosen AB-cycle with its B-edges
+    let mut adj: Vec<Vec<u16>> = adj_a.iter().map(|a| a.to_vec()).collect();
+    for (k, w) in cycle.windows(2).enumerate() {
+        let (u, v) = (w[0] as usize, w[1] as usize);
+        if k % 2 == 0 {
+            remove_edge(&mut adj, u, v);
+        } else {
+            adj[u].push(v as u16);
+            adj[v].push(u as u16);
+        }
+    }
+
+    merge_subtours(&mut adj, distance_matrix);
+    adjacency_to_tour(&adj, p1[0])
+}
+
+fn adjacency(tour: &[u16]) -> Vec<[u16; 2]> {
+    let n = tour.len();
+    let mut adj = vec![[0; 2]; n];
+    for i in 0..n {
+        adj[tour[i] as usize] = [tour[(i + n - 1) % n], tour[(i + 1) % n]];
+    }
+    adj
+}
+
+fn remove_edge(adj: &mut [Vec<u16>], u: usize, v: usize) {
+    let i = adj[u].iter().position(|&c| c as usize == v).unwrap();
+    adj[u].swap_remove(i);
+    let i = adj[v].iter().position(|&c| c as usize == u).unwrap();
+    adj[v].swap_remove(i);
+}
+
+/// Decomposes the symmetric difference of two tours into AB-cycles, given as closed node
+/// sequences whose edges alternate between the first (even steps) and second parent.
+fn ab_cycles<R: Rng>(rem_a: &mut [Vec<u16>], rem_b: &mut [Vec<u16>], rng: &mut R) -> Vec<Vec<u16>> {
+    let n = rem_a.len();
+    let mut cycles = vec![];
+    let mut on_path: Vec<Vec<usize>> = vec![vec![]; n];
+
+    for start in 0..n {
+        while !rem_a[start].is_empty() {
+            let mut path = vec![start as u16];
+            on_path[start].push(0);
+
+            loop {
+                let last = *path.last().unwrap() as usize;
+                let take_a = (path.len() - 1) % 2 == 0;
+                let rem = match take_a {
+                    true => &mut *rem_a,
+                    false => &mut *rem_b,
+                };
+                if rem[last].is_empty() {
+                    break;
+                }
+                let next = *rem[last].choose(rng).unwrap() as usize;
+                remove_edge(rem, last, next);
+                path.push(next as u16);
+
+                //close a cycle when we come back to a node with the same parity
+                let m = path.len() - 1;
+                if let Some(&k) = on_path[next].iter().find(|&&k| k % 2 == m % 2) {
+                    let mut cycle = path[k..].to_vec();
+                    if k % 2 == 1 {
+                        //rotate so that the cycle starts with an edge of the first parent
+                        cycle.remove(0);
+                        cycle.push(cycle[0]);
+                    }
+                    cycles.push(cycle);
+                    for &c in &path[k + 1..] {
+                        on_path[c as usize].retain(|&i| i <= k);
+                    }
+                    path.truncate(k + 1);
+                    if path.len() == 1 {
+                        break;
+                    }
+                } else {
+                    on_path[next].push(m);
+                }
+            }
+
+            for &c in &path {
+                on_path[c as usize].clear();
+            }
+        }
+    }
+
+    cycles
+}
+
+/// Joins subtours of a 2-regular graph into a single tour with the cheapest 2-exchanges
+fn merge_subtours(adj: &mut [Vec<u16>], distance_matrix: &[Vec<i32>]) {
+    let d = |a: usize, b: usize| distance_matrix[a][b];
+
+    loop {
+        let subtours = subtour_labels(adj);
+        let count = *subtours.iter().max().unwrap() + 1;
+        if count == 1 {
+            return;
+        }
+
+        let mut sizes = vec![0; count];
+        for &s in &subtours {
+            sizes[s] += 1;
+        }
+        let smallest = (0..count).min_by_key(|&s| sizes[s]).unwrap();
+
+        let mut best = None;
+        let mut best_cost = i32::MAX;
+        for u1 in (0..adj.len()).filter(|&u| subtours[u] == smallest) {
+            for &u2 in &adj[u1] {
+                let u2 = u2 as usize;
+                for v1 in (0..adj.len()).filter(|&v| subtours[v] != smallest) {
+                    for &v2 in &adj[v1] {
+                        let v2 = v2 as usize;
+                        let removed = d(u1, u2) + d(v1, v2);
+                        let straight = d(u1, v1) + d(u2, v2) - removed;
+                        let crossed = d(u1, v2) + d(u2, v1) - removed;
+                        if straight < best_cost {
+                            best_cost = straight;
+                            best = Some((u1, u2, v1, v2));
+                        }
+                        if crossed < best_cost {
+                            best_cost = crossed;
+                            best = Some((u1, u2, v2, v1));
+                        }
+                    }
+                }
+            }
+        }
+
+        let (u1, u2, v1, v2) = best.unwrap();
+        remove_edge(adj, u1, u2);
+        remove_edge(adj, v1, v2);
+        adj[u1].push(v1 as u16);
+        adj[v1].push(u1 as u16);
+        adj[u2].push(v2 as u16);
+        adj[v2].push(u2 as u16);
+    }
+}
+
+fn subtour_labels(adj: &[Vec<u16>]) -> Vec<usize> {
+    let mut labels = vec![usize::MAX; adj.len()];
+    let mut label = 0;
+    for start in 0..adj.len() {
+        if labels[start] != usize::MAX {
+            continue;
+        }
+        let mut stack = vec![start];
+        while let Some(v) = stack.pop() {
+            if labels[v] != usize::MAX {
+                continue;
+            }
+            labels[v] = label;
+            stack.extend(adj[v].iter().map(|&c| c as usize));
+        }
+        label += 1;
+    }
+    labels
+}
+
+fn adjacency_to_tour(adj: &[Vec<u16>], first: u16) -> Vec<u16> {
+    let mut tour = Vec::with_capacity(adj.len());
+    let mut prev = first;
+    let mut current = first;
+    loop {
+        tour.push(current);
+        let next = match adj[current as usize][0] == prev && tour.len() > 1 {
+            true => adj[current as usize][1],
+            false => adj[current as usize][0],
+        };
+        if next == first || tour.len() == adj.len() {
+            break;
+        }
+        prev = current;
+        current = next;
+    }
+    tour
+}
+
+impl FromStr for Selection {
+    type Err = String;
+
+    /// Accepts `roulette`, `tournament` (k = 3) or `tournament:<k>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "roulette" => Ok(Self::Roulette),
+            None if s == "tournament" => Ok(Self::Tournament(3)),
+            Some(("tournament", k)) => k.parse().map(Self::Tournament).map_err(|_| s.to_owned()),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+impl FromStr for Crossover {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ox" => Ok(Self::Ox),
+            "pmx" => Ok(Self::Pmx),
+            "cx" => Ok(Self::Cx),
+            "erx" => Ok(Self::Erx),
+            "eax" => Ok(Self::Eax),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+impl FromStr for Mutation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "swap" => Ok(Self::Swap),
+            "inversion" => Ok(Self::Inversion),
+            "scramble" => Ok(Self::Scramble),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Genetic {
+    pub config: GeneticConfig,
+}
+
+impl Solver for Genetic {
+    fn name(&self) -> &'static str {
+        "ga"
+    }
+
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError> {
+        let config = &mut self.config;
+        match parameter {
+            "population_size" => config.population_size = parse_param(parameter, value)?,
+            "generations" => config.generations = parse_param(parameter, value)?,
+            "selection" => config.selection = parse_param(parameter, value)?,
+            "crossover" => config.crossover = parse_param(parameter, value)?,
+            "crossover_rate" => config.crossover_rate = parse_param(parameter, value)?,
+            "mutation" => config.mutation = parse_param(parameter, value)?,
+            "mutation_rate" => config.mutation_rate = parse_param(parameter, value)?,
+            "elitism" => config.elitism = parse_param(parameter, value)?,
+            "memetic" => config.memetic = parse_param(parameter, value)?,
+            "preserve_first" => config.preserve_first = parse_param(parameter, value)?,
+            "seed" => config.seed = Some(parse_param(parameter, value)?),
+            _ => return Err(ConfigError::UnknownParameter(parameter.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution {
+        let mut tracker = Tracker::new(budget, cancel, on_improvement);
+        let (tour, len) = genetic_tracked(distance_matrix, &self.config, initial, &mut tracker);
+        tracker.solution(tour, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{circle_matrix, is_permutation};
+
+    #[test]
+    fn crossovers_produce_permutations() {
+        let n = 12;
+        let matrix = circle_matrix(n);
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        for crossover in [
+            Crossover::Ox,
+            Crossover::Pmx,
+            Crossover::Cx,
+            Crossover::Erx,
+            Crossover::Eax,
+        ] {
+            for _ in 0..50 {
+                let p1 = random_solution(n as u16, Some(rng.gen()), false);
+                let p2 = random_solution(n as u16, Some(rng.gen()), false);
+                let child = crossover.apply(&p1, &p2, &matrix, &mut rng);
+                assert!(is_permutation(&child, n), "{:?}: {:?}", crossover, child);
+            }
+            //too short to cut, the parent is returned
+            for parent in [vec![], vec![0]] {
+                let child = crossover.apply(&parent, &parent, &matrix, &mut rng);
+                assert_eq!(child, parent, "{:?}", crossover);
+            }
+        }
+
+        //large enough that a quadratic edge recombination would stand out
+        let n = 20000;
+        let p1 = random_solution(n as u16, Some(1), false);
+        let p2 = random_solution(n as u16, Some(2), false);
+        assert!(is_permutation(&edge_recombination(&p1, &p2, &mut rng), n));
+    }
+
+    #[test]
+    fn genetic_finds_circle_optimum() {
+        let matrix = circle_matrix(10);
+        let optimum = tour_len(&(0..10).collect::<Vec<u16>>(), &matrix);
+        let config = GeneticConfig {
+            crossover: Crossover::Eax,
+            preserve_first: true,
+            seed: Some(1),
+            ..Default::default()
+        };
+
+        let (tour, len) = genetic(&matrix, &config);
+        assert_eq!(tour[0], 0);
+        assert_eq!(len, optimum);
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::{self, available_parallelism},
+};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    algorithms::{hillclimb::swap_delta, two_opt::two_opt},
+    helpers::tour_len,
+    solver::{
+        parse_param, Budget, CancelToken, ConfigError, Improvement, Solution, Solver, Tracker,
+    },
+};
+
+/// Greedy construction randomized by GRASP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Construction {
+    NearestNeighbor,
+    CheapestInsertion,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraspConfig {
+    pub construction: Construction,
+    /// Restricted candidate list threshold, 0 is purely greedy and 1 purely random
+    pub alpha: f64,
+    pub iterations: usize,
+    /// Size of the elite pool used for path relinking, 0 disables relinking
+    pub elite_size: usize,
+    /// Number of worker threads, 0 uses all available cores
+    pub threads: usize,
+    pub preserve_first: bool,
+    pub seed: Option<u64>,
+}
+
+impl Default for GraspConfig {
+    fn default() -> Self {
+        Self {
+            construction: Construction::NearestNeighbor,
+            alpha: 0.2,
+            iterations: 100,
+            elite_size: 5,
+            threads: 0,
+            preserve_first: false,
+            seed: None,
+        }
+    }
+}
+
+/// Greedy Randomized Adaptive Search Procedure: every iteration builds a tour with a randomized
+/// greedy construction and improves it with 2-opt, optionally relinking it with an elite solution.
+pub fn grasp(distance_matrix: &[Vec<i32>], config: &GraspConfig) -> (Vec<u16>, i32) {
+    grasp_tracked(distance_matrix, config, None, &mut Tracker::unlimited())
+}
+
+fn grasp_tracked(
+    distance_matrix: &[Vec<i32>],
+    config: &GraspConfig,
+    initial: Option<&[u16]>,
+    tracker: &mut Tracker,
+) -> (Vec<u16>, i32) {
+    let base_seed = config
+        .seed
+        .unwrap_or_else(|| ChaCha8Rng::from_entropy().gen());
+    let threads = match config.threads {
+        0 => available_parallelism().map(|n| n.get()).unwrap_or(1),
+        t => t,
+    };
+
+    let mut elite: Vec<(Vec<u16>, i32)> = vec![];
+    let mut best: Option<(Vec<u16>, i32)> = None;
+    let mut accept = |tour: Vec<u16>, len: i32, elite: &mut Vec<(Vec<u16>, i32)>| {
+        let tour = rotate_to_first(tour);
+        if best.as_ref().is_none_or(|(_, best_len)| len < *best_len) {
+            best = Some((tour.clone(), len));
+        }
+        update_elite(elite, tour, len, config.elite_size);
+        len
+    };
+
+    if let Some(tour) = initial {
+        let (tour, len) = two_opt(tour, distance_matrix, config.preserve_first);
+        tracker.evaluated(1);
+        tracker.improved(accept(tour, len, &mut elite));
+    }
+
+    let mut next = 0;
+    'batches: while next < config.iterations && !tracker.should_stop() {
+        let batch = next..(next + threads).min(config.iterations);
+        next = batch.end;
+        let constructed = construct_batch(distance_matrix, config, threads, base_seed, batch);
+
+        //relinking runs in iteration order so results do not depend on thread count
+        for (tour, len) in constructed {
+            if tracker.should_stop() {
+                break 'batches;
+            }
+            let (mut tour, mut len) = (rotate_to_first(tour), len);
+            tracker.iteration();
+            tracker.evaluated(1);
+
+            if let Some(guide) = most_different(&elite, &tour) {
+                let (relinked, relinked_len) = path_relinking(&tour, guide, distance_matrix);
+                if relinked_len < len {
+                    (tour, len) = two_opt(&relinked, distance_matrix, true);
+                }
+                tracker.evaluated(1);
+            }
+            tracker.improved(accept(tour, len, &mut elite));
+        }
+    }
+
+    best.unwrap_or_else(|| {
+        let tour = (0..distance_matrix.len() as u16).collect::<Vec<u16>>();
+        let len = tour_len(&tour, distance_matrix);
+        (tour, len)
+    })
+}
+
+fn construct_batch(
+    distance_matrix: &[Vec<i32>],
+    config: &GraspConfig,
+    threads: usize,
+    base_seed: u64,
+    batch: std::ops::Range<usize>,
+) -> Vec<(Vec<u16>, i32)> {
+    let next_iteration = AtomicUsize::new(batch.start);
+
+    let mut built: Vec<(usize, Vec<u16>, i32)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads.min(batch.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut built = vec![];
+                    loop {
+                        let iteration = next_iteration.fetch_add(1, Ordering::Relaxed);
+                        if iteration >= batch.end {
+                            break;
+                        }
+                        let mut rng = ChaCha8Rng::seed_from_u64(base_seed);
+                        rng.set_stream(iteration as u64);
+
+                        let tour = randomized_construction(distance_matrix, config, &mut rng);
+                        let (tour, len) = two_opt(&tour, distance_matrix, config.preserve_first);
+                        built.push((iteration, tour, len));
+                    }
+                    built
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("GRASP worker thread panicked"))
+            .collect()
+    });
+
+    built.sort_by_key(|(iteration, _, _)| *iteration);
+    built
+        .into_iter()
+        .map(|(_, tour, len)| (tour, len))
+        .collect()
+}
+
+/// Builds a tour choosing every step uniformly from the restricted candidate list, which holds
+/// all candidates costing at most min + alpha * (max - min)
+pub fn randomized_construction<R: Rng>(
+    distance_matrix: &[Vec<i32>],
+    config: &GraspConfig,
+    rng: &mut R,
+) -> Vec<u16> {
+    let n = distance_matrix.len();
+    if n == 0 {
+        return vec![];
+    }
+    let start = match config.preserve_first {
+        true => 0,
+        false => rng.gen_range(0..n),
+    };
+
+    let mut tour = vec![start as u16];
+    let mut open: Vec<usize> = (0..n).filter(|&c| c != start).collect();
+
+    while !open.is_empty() {
+        //cost of every open city and where it would be inserted
+        let costs: Vec<(i32, usize)> = match config.construction {
+            Construction::NearestNeighbor => {
+                let last = *tour.last().unwrap() as usize;
+                open.iter()
+                    .map(|&c| (distance_matrix[last][c], tour.len()))
+                    .collect()
+            }
+            Construction::CheapestInsertion => open
+                .iter()
+                .map(|&c| cheapest_insertion(&tour, c, distance_matrix))
+                .collect(),
+        };
+
+        let min = costs.iter().map(|(cost, _)| *cost).min().unwrap();
+        let max = costs.iter().map(|(cost, _)| *cost).max().unwrap();
+        let threshold = min as f64 + config.alpha * (max - min) as f64;
+        let rcl: Vec<usize> = (0..open.len())
+            .filter(|&i| costs[i].0 as f64 <= threshold)
+            .collect();
+
+        let chosen = *rcl.choose(rng).unwrap();
+        tour.insert(costs[chosen].1, open[chosen] as u16);
+        open.swap_remove(chosen);
+    }
+
+    tour
+}
+
+/// Returns the cost and position of the cheapest insertion of city into tour
+fn cheapest_insertion(tour: &[u16], city: usize, distance_matrix: &[Vec<i32>]) -> (i32, usize) {
+    (0..tour.len())
+        .map(|k| {
+            let a = tour[k] as usize;
+            let b = tour[(k + 1) % tour.len()] as usize;
+            let cost = distance_matrix[a][city] + distance_matrix[city][b] - distance_matrix[a][b];
+            (cost, k + 1)
+        })
+        .min()
+        .unwrap()
+}
+
+/// Walks from start towards guide by swaps that fix one position at a time, always taking
+/// the cheapest one, and returns the best intermediate tour. Both tours must begin with the same city.
+pub fn path_relinking(
+    start: &[u16],
+    guide: &[u16],
+    distance_matrix: &[Vec<i32>],
+) -> (Vec<u16>, i32) {
+    let mut tour = start.to_vec();
+    let mut len = tour_len(&tour, distance_matrix);
+    let mut best = (tour.clone(), len);
+
+    let mut pos = vec![0; tour.len()];
+    for (i, &city) in tour.iter().enumerate() {
+        pos[city as usize] = i;
+    }
+
+    loop {
+        let step = (0..tour.len())
+            .filter(|&i| tour[i] != guide[i])
+            .map(|i| {
+                let j = pos[guide[i] as usize];
+                (swap_delta(&tour, i, j, distance_matrix), i, j)
+            })
+            .min();
+        let Some((delta, i, j)) = step else {
+            break;
+        };
+
+        tour.swap(i, j);
+        pos[tour[i] as usize] = i;
+        pos[tour[j] as usize] = j;
+        len += delta;
+
+        //the guide itself is not a new solution
+        if len < best.1 && tour != guide {
+            best = (tour.clone(), len);
+        }
+    }
+
+    best
+}
+
+fn most_different<'a>(elite: &'a [(Vec<u16>, i32)], tour: &[u16]) -> Option<&'a [u16]> {
+    elite
+        .iter()
+        .map(|(e, _)| (e.iter().zip(tour).filter(|(a, b)| a != b).count(), e))
+        .filter(|(differences, _)| *differences > 0)
+        .max_by_key(|(differences, _)| *differences)
+        .map(|(_, e)| e.as_slice())
+}
+
+/// Keeps the pool sorted by length, free of duplicates and at most size long
+fn update_elite(elite: &mut Vec<(Vec<u16>, i32)>, tour: Vec<u16>, len: i32, size: usize) {
+    if elite.iter().any(|(e, _)| *e == tour) {
+        return;
+    }
+    let index = elite.partition_point(|(_, l)| *l <= len);
+    if index < size {
+        elite.insert(index, (tour, len));
+        elite.truncate(size);
+    }
+}
+
+fn rotate_to_first(mut tour: Vec<u16>) -> Vec<u16> {
+    if let Some(pos) = tour.iter().position(|&c| c == 0) {
+        tour.rotate_left(pos);
+    }
+    tour
+}
+
+impl FromStr for Construction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nn" => Ok(Self::NearestNeighbor),
+            "insertion" => Ok(Self::CheapestInsertion),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Grasp {
+    pub config: GraspConfig,
+}
+
+impl Solver for Grasp {
+    fn name(&self) -> &'static str {
+        "grasp"
+    }
+
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError> {
+        let config = &mut self.config;
+        match parameter {
+            "construction" => config.construction = parse_param(parameter, value)?,
+            "alpha" => config.alpha = parse_param(parameter, value)?,
+            "iterations" => config.iterations = parse_param(parameter, value)?,
+            "elite_size" => config.elite_size = parse_param(parameter, value)?,
+            "threads" => config.threads = parse_param(parameter, value)?,
+            "preserve_first" => config.preserve_first = parse_param(parameter, value)?,
+            "seed" => config.seed = Some(parse_param(parameter, value)?),
+            _ => return Err(ConfigError::UnknownParameter(parameter.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution {
+        let mut tracker = Tracker::new(budget, cancel, on_improvement);
+        let (tour, len) = grasp_tracked(distance_matrix, &self.config, initial, &mut tracker);
+        tracker.solution(tour, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{circle_matrix, is_permutation};
+
+    #[test]
+    fn constructions_find_circle_optimum() {
+        let matrix = circle_matrix(16);
+        let optimum = tour_len(&(0..16).collect::<Vec<u16>>(), &matrix);
+
+        for construction in [
+            Construction::NearestNeighbor,
+            Construction::CheapestInsertion,
+        ] {
+            let config = GraspConfig {
+                construction,
+                alpha: 0.5,
+                iterations: 20,
+                threads: 2,
+                seed: Some(3),
+                ..Default::default()
+            };
+            let (tour, len) = grasp(&matrix, &config);
+            assert!(is_permutation(&tour, 16));
+            assert_eq!(len, optimum);
+        }
+    }
+
+    #[test]
+    fn results_do_not_depend_on_thread_count() {
+        let matrix = circle_matrix(25);
+        let run = |threads| {
+            let config = GraspConfig {
+                alpha: 1.0,
+                iterations: 10,
+                threads,
+                seed: Some(11),
+                ..Default::default()
+            };
+            grasp(&matrix, &config)
+        };
+        assert_eq!(run(1), run(3));
+    }
+
+    #[test]
+    fn path_relinking_never_returns_worse_start() {
+        let matrix = circle_matrix(12);
+        let start: Vec<u16> = vec![0, 5, 2, 9, 1, 11, 3, 7, 4, 10, 6, 8];
+        let guide: Vec<u16> = (0..12).collect();
+        let (tour, len) = path_relinking(&start, &guide, &matrix);
+        assert!(is_permutation(&tour, 12));
+        assert!(len < tour_len(&start, &matrix));
+        assert_eq!(len, tour_len(&tour, &matrix));
+    }
+}
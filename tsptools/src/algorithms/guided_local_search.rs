@@ -0,0 +1,231 @@
+use crate::{
+    algorithms::two_opt::{two_opt, two_opt_delta},
+    helpers::{random_solution, tour_len},
+    solver::{
+        parse_param, Budget, CancelToken, ConfigError, Improvement, Solution, Solver, Tracker,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct GlsConfig {
+    /// Lambda is this factor times the average edge length of the first local optimum
+    pub alpha: f64,
+    /// Number of penalty rounds
+    pub iterations: usize,
+    pub preserve_first: bool,
+    pub seed: Option<u64>,
+}
+
+impl Default for GlsConfig {
+    fn default() -> Self {
+        Self {
+            alpha: 0.3,
+            iterations: 1000,
+            preserve_first: false,
+            seed: None,
+        }
+    }
+}
+
+/// Guided Local Search on top of 2-opt. Local optima are escaped by penalising their
+/// edges with the highest utility d / (1 + penalty) and continuing a fast local search
+/// on the augmented cost d + lambda * penalty, restricted to cities whose activation bit is set.
+pub fn guided_local_search(distance_matrix: &[Vec<i32>], config: &GlsConfig) -> (Vec<u16>, i32) {
+    guided_local_search_tracked(distance_matrix, config, None, &mut Tracker::unlimited())
+}
+
+fn guided_local_search_tracked(
+    distance_matrix: &[Vec<i32>],
+    config: &GlsConfig,
+    initial: Option<&[u16]>,
+    tracker: &mut Tracker,
+) -> (Vec<u16>, i32) {
+    let n = distance_matrix.len();
+    let starting_tour = match initial {
+        Some(tour) => tour.to_vec(),
+        None => random_solution(n as u16, config.seed, config.preserve_first),
+    };
+
+    //reversals never touch position 0, so the first city stays in place
+    let (mut tour, mut best_len) = two_opt(&starting_tour, distance_matrix, false);
+    let mut best = tour.clone();
+    tracker.evaluated(1);
+    tracker.improved(best_len);
+
+    if n < 5 {
+        return (best, best_len);
+    }
+
+    let lambda = config.alpha * best_len as f64 / n as f64;
+    let mut penalties = vec![vec![0u32; n]; n];
+    let mut active = vec![false; n];
+    let mut pos = vec![0; n];
+    for (i, &city) in tour.iter().enumerate() {
+        pos[city as usize] = i;
+    }
+
+    for _ in 0..config.iterations {
+        if tracker.should_stop() {
+            break;
+        }
+
+        penalize(&tour, distance_matrix, &mut penalties, &mut active);
+        let evaluated = fast_local_search(
+            &mut tour,
+            &mut pos,
+            distance_matrix,
+            &penalties,
+            lambda,
+            &mut active,
+        );
+        tracker.iteration();
+        tracker.evaluated(evaluated);
+
+        let len = tour_len(&tour, distance_matrix);
+        if len < best_len {
+            best_len = len;
+            best = tour.clone();
+            tracker.improved(best_len);
+        }
+    }
+
+    (best, best_len)
+}
+
+/// Penalises the edges of maximal utility and activates their endpoints
+fn penalize(
+    tour: &[u16],
+    distance_matrix: &[Vec<i32>],
+    penalties: &mut [Vec<u32>],
+    active: &mut [bool],
+) {
+    let n = tour.len();
+    let edges = (0..n).map(|i| (tour[i] as usize, tour[(i + 1) % n] as usize));
+    let utility =
+        |(a, b): (usize, usize)| distance_matrix[a][b] as f64 / (1.0 + penalties[a][b] as f64);
+
+    let max_utility = edges.clone().map(utility).fold(f64::MIN, f64::max);
+    let selected: Vec<(usize, usize)> = edges.filter(|&e| utility(e) >= max_utility).collect();
+
+    for (a, b) in selected {
+        penalties[a][b] += 1;
+        penalties[b][a] += 1;
+        active[a] = true;
+        active[b] = true;
+    }
+}
+
+/// 2-opt on augmented costs driven by activation bits, returns the number of moves evaluated
+fn fast_local_search(
+    tour: &mut [u16],
+    pos: &mut [usize],
+    distance_matrix: &[Vec<i32>],
+    penalties: &[Vec<u32>],
+    lambda: f64,
+    active: &mut [bool],
+) -> u64 {
+    let n = tour.len();
+    let mut evaluated = 0;
+    let augmented_delta = |tour: &[u16], i: usize, j: usize| {
+        let (a, b) = (tour[i] as usize, tour[i + 1] as usize);
+        let (c, d) = (tour[j] as usize, tour[(j + 1) % n] as usize);
+        let penalty = penalties[a][c] as f64 + penalties[b][d] as f64
+            - penalties[a][b] as f64
+            - penalties[c][d] as f64;
+        two_opt_delta(tour, i, j, distance_matrix) as f64 + lambda * penalty
+    };
+
+    while let Some(city) = active.iter().position(|&a| a) {
+        let p = pos[city];
+        let mut improved = false;
+
+        //moves removing the edge after or before the city
+        'edges: for i in [p, (p + n - 1) % n] {
+            for k in 0..n {
+                let (a, b) = (i.min(k), i.max(k));
+                if b == a || b == a + 1 || (a == 0 && b == n - 1) {
+                    continue;
+                }
+                evaluated += 1;
+                if augmented_delta(tour, a, b) < -1e-9 {
+                    let touched = [tour[a], tour[a + 1], tour[b], tour[(b + 1) % n]];
+                    tour[a + 1..=b].reverse();
+                    for q in a + 1..=b {
+                        pos[tour[q] as usize] = q;
+                    }
+                    for c in touched {
+                        active[c as usize] = true;
+                    }
+                    improved = true;
+                    break 'edges;
+                }
+            }
+        }
+
+        if !improved {
+            active[city] = false;
+        }
+    }
+
+    evaluated
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GuidedLocalSearch {
+    pub config: GlsConfig,
+}
+
+impl Solver for GuidedLocalSearch {
+    fn name(&self) -> &'static str {
+        "gls"
+    }
+
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError> {
+        let config = &mut self.config;
+        match parameter {
+            "alpha" => config.alpha = parse_param(parameter, value)?,
+            "iterations" => config.iterations = parse_param(parameter, value)?,
+            "preserve_first" => config.preserve_first = parse_param(parameter, value)?,
+            "seed" => config.seed = Some(parse_param(parameter, value)?),
+            _ => return Err(ConfigError::UnknownParameter(parameter.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution {
+        let mut tracker = Tracker::new(budget, cancel, on_improvement);
+        let (tour, len) =
+            guided_local_search_tracked(distance_matrix, &self.config, initial, &mut tracker);
+        tracker.solution(tour, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::circle_matrix;
+
+    #[test]
+    fn gls_finds_circle_optimum() {
+        let matrix = circle_matrix(20);
+        let optimum = tour_len(&(0..20).collect::<Vec<u16>>(), &matrix);
+        let config = GlsConfig {
+            iterations: 200,
+            preserve_first: true,
+            seed: Some(5),
+            ..Default::default()
+        };
+
+        let (tour, len) = guided_local_search(&matrix, &config);
+        assert_eq!(tour[0], 0);
+        assert_eq!(len, optimum);
+        assert_eq!(len, tour_len(&tour, &matrix));
+    }
+}
@@ -1,7 +1,13 @@
-use crate::helpers::{random_solution, tour_len};
+use crate::{
+    helpers::{random_solution, tour_len},
+    solver::{
+        parse_param, Budget, CancelToken, ConfigError, Improvement, Solution, Solver, Tracker,
+    },
+    trace::{Move, MoveKind, Trace},
+};
 
 pub fn hillclimb_rand(
-    distance_matrix: &Vec<Vec<i32>>,
+    distance_matrix: &[Vec<i32>],
     seed: Option<u64>,
     preserve_first: bool,
 ) -> (Vec<u16>, i32) {
@@ -10,28 +16,81 @@ pub fn hillclimb_rand(
 }
 
 pub fn hillclimb(
-    starting_tour: &Vec<u16>,
-    distance_matrix: &Vec<Vec<i32>>,
+    starting_tour: &[u16],
+    distance_matrix: &[Vec<i32>],
     preserve_first: bool,
 ) -> (Vec<u16>, i32) {
-    let mut current_tour = starting_tour.clone();
+    hillclimb_tracked(
+        starting_tour,
+        distance_matrix,
+        preserve_first,
+        &mut Tracker::unlimited(),
+        &mut (),
+    )
+}
+
+/// Same as [`hillclimb`], passing every accepted swap to `trace`
+pub fn hillclimb_traced<T: Trace>(
+    starting_tour: &[u16],
+    distance_matrix: &[Vec<i32>],
+    preserve_first: bool,
+    trace: &mut T,
+) -> (Vec<u16>, i32) {
+    hillclimb_tracked(
+        starting_tour,
+        distance_matrix,
+        preserve_first,
+        &mut Tracker::unlimited(),
+        trace,
+    )
+}
+
+fn hillclimb_tracked<T: Trace>(
+    starting_tour: &[u16],
+    distance_matrix: &[Vec<i32>],
+    preserve_first: bool,
+    tracker: &mut Tracker,
+    trace: &mut T,
+) -> (Vec<u16>, i32) {
+    let mut current_tour = starting_tour.to_vec();
     let mut current_len = tour_len(&current_tour, distance_matrix);
+    tracker.evaluated(1);
+    tracker.improved(current_len);
+    let mut step = 0;
 
-    loop {
-        let neighbors = get_neighbors(&current_tour, preserve_first);
-        let (best_neighbor, best_neighbor_len) = get_best_neighbor(&neighbors, distance_matrix);
+    while !tracker.should_stop() {
+        let mut neighbors = get_neighbors(&current_tour, preserve_first);
+        if neighbors.is_empty() {
+            break;
+        }
+        let (best_index, best_neighbor_len) = get_best_neighbor(&neighbors, distance_matrix);
+        tracker.iteration();
+        tracker.evaluated(neighbors.len() as u64);
 
         if best_neighbor_len >= current_len {
             break;
         }
-        current_tour = best_neighbor;
+        if T::ENABLED {
+            let (i, j) = swap_positions(best_index, current_tour.len(), preserve_first);
+            step += 1;
+            trace.accepted(Move {
+                kind: MoveKind::Swap,
+                i,
+                j,
+                delta: best_neighbor_len - current_len,
+                len: best_neighbor_len,
+                step,
+            });
+        }
+        current_tour = neighbors.swap_remove(best_index);
         current_len = best_neighbor_len;
+        tracker.improved(current_len);
     }
 
     (current_tour, current_len)
 }
 
-fn get_neighbors(path: &Vec<u16>, preserve_first: bool) -> Vec<Vec<u16>> {
+fn get_neighbors(path: &[u16], preserve_first: bool) -> Vec<Vec<u16>> {
     let mut neighbors = vec![];
 
     let start = match preserve_first {
@@ -41,7 +100,7 @@ fn get_neighbors(path: &Vec<u16>, preserve_first: bool) -> Vec<Vec<u16>> {
 
     for i in start..path.len() {
         for j in i + 1..path.len() {
-            let mut neighbor = path.clone();
+            let mut neighbor = path.to_vec();
             neighbor.swap(i, j);
             neighbors.push(neighbor);
         }
@@ -50,10 +109,22 @@ fn get_neighbors(path: &Vec<u16>, preserve_first: bool) -> Vec<Vec<u16>> {
     neighbors
 }
 
-fn get_best_neighbor(
-    neighbors: &Vec<Vec<u16>>,
-    distance_matrix: &Vec<Vec<i32>>,
-) -> (Vec<u16>, i32) {
+/// Positions swapped by the neighbour at `index` of [`get_neighbors`]
+fn swap_positions(index: usize, n: usize, preserve_first: bool) -> (usize, usize) {
+    let mut i = match preserve_first {
+        true => 1,
+        false => 0,
+    };
+    let mut index = index;
+    while index >= n - i - 1 {
+        index -= n - i - 1;
+        i += 1;
+    }
+    (i, i + 1 + index)
+}
+
+/// Index and length of the shortest neighbour
+fn get_best_neighbor(neighbors: &[Vec<u16>], distance_matrix: &[Vec<i32>]) -> (usize, i32) {
     let mut best_len = tour_len(&neighbors[0], distance_matrix);
     let mut best_neighbor_index = 0;
 
@@ -65,5 +136,109 @@ fn get_best_neighbor(
         }
     }
 
-    (neighbors[best_neighbor_index].clone(), best_len)
+    (best_neighbor_index, best_len)
+}
+
+/// Change of tour length after swapping cities at positions i and j
+pub fn swap_delta(tour: &[u16], i: usize, j: usize, distance_matrix: &[Vec<i32>]) -> i32 {
+    let n = tour.len();
+    let swapped = |p: usize| match p {
+        p if p == i => tour[j],
+        p if p == j => tour[i],
+        p => tour[p],
+    };
+
+    //positions where an edge affected by the swap starts
+    let mut starts = [(i + n - 1) % n, i, (j + n - 1) % n, j];
+    starts.sort_unstable();
+
+    let mut delta = 0;
+    for (k, &p) in starts.iter().enumerate() {
+        if k > 0 && starts[k - 1] == p {
+            continue;
+        }
+        let q = (p + 1) % n;
+        delta += distance_matrix[swapped(p) as usize][swapped(q) as usize]
+            - distance_matrix[tour[p] as usize][tour[q] as usize];
+    }
+    delta
+}
+
+/// Best-improvement local search in the pair swap neighborhood
+#[derive(Debug, Clone, Default)]
+pub struct HillClimb {
+    pub preserve_first: bool,
+    pub seed: Option<u64>,
+}
+
+impl Solver for HillClimb {
+    fn name(&self) -> &'static str {
+        "hc"
+    }
+
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError> {
+        match parameter {
+            "preserve_first" => self.preserve_first = parse_param(parameter, value)?,
+            "seed" => self.seed = Some(parse_param(parameter, value)?),
+            _ => return Err(ConfigError::UnknownParameter(parameter.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution {
+        let mut tracker = Tracker::new(budget, cancel, on_improvement);
+        let starting_tour = match initial {
+            Some(tour) => tour.to_vec(),
+            None => random_solution(distance_matrix.len() as u16, self.seed, self.preserve_first),
+        };
+
+        let (tour, len) = hillclimb_tracked(
+            &starting_tour,
+            distance_matrix,
+            self.preserve_first,
+            &mut tracker,
+            &mut (),
+        );
+        tracker.solution(tour, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::circle_matrix, trace::descent_curve};
+
+    #[test]
+    fn trace_records_the_descent() {
+        let matrix = circle_matrix(9);
+        let start = random_solution(9, Some(4), true);
+        let mut moves = vec![];
+        let traced = hillclimb_traced(&start, &matrix, true, &mut moves);
+        assert_eq!(traced, hillclimb(&start, &matrix, true));
+
+        let mut tour = start.clone();
+        let mut len = tour_len(&tour, &matrix);
+        for (k, step) in moves.iter().enumerate() {
+            assert_eq!(step.kind, MoveKind::Swap);
+            assert_eq!(step.step, k as u64 + 1);
+            assert!(0 < step.i && step.i < step.j && step.delta < 0);
+            assert_eq!(swap_delta(&tour, step.i, step.j, &matrix), step.delta);
+            tour.swap(step.i, step.j);
+            len += step.delta;
+            assert_eq!(tour_len(&tour, &matrix), step.len);
+            assert_eq!(len, step.len);
+        }
+        assert_eq!((tour, len), traced);
+
+        let curve = descent_curve(tour_len(&start, &matrix), &moves);
+        assert_eq!(curve.len(), moves.len() + 1);
+        assert!(curve.windows(2).all(|w| w[1] < w[0]));
+    }
 }
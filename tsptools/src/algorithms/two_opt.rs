@@ -1,7 +1,15 @@
-use crate::helpers::*;
+use std::collections::VecDeque;
+
+use crate::{
+    helpers::{tour_repr::Tour, *},
+    solver::{
+        parse_param, Budget, CancelToken, ConfigError, Improvement, Solution, Solver, Tracker,
+    },
+    trace::{Move, MoveKind, Trace},
+};
 
 pub fn two_opt_random(
-    distance_matrix: &Vec<Vec<i32>>,
+    distance_matrix: &[Vec<i32>],
     seed: Option<u64>,
     preserve_first: bool,
 ) -> (Vec<u16>, i32) {
@@ -10,15 +18,51 @@ pub fn two_opt_random(
 }
 
 pub fn two_opt(
-    starting_tour: &Vec<u16>,
-    distance_matrix: &Vec<Vec<i32>>,
+    starting_tour: &[u16],
+    distance_matrix: &[Vec<i32>],
+    preserve_first: bool,
+) -> (Vec<u16>, i32) {
+    two_opt_tracked(
+        starting_tour,
+        distance_matrix,
+        preserve_first,
+        &mut Tracker::unlimited(),
+        &mut (),
+    )
+}
+
+/// Same as [`two_opt`], passing every accepted reversal to `trace`
+pub fn two_opt_traced<T: Trace>(
+    starting_tour: &[u16],
+    distance_matrix: &[Vec<i32>],
+    preserve_first: bool,
+    trace: &mut T,
+) -> (Vec<u16>, i32) {
+    two_opt_tracked(
+        starting_tour,
+        distance_matrix,
+        preserve_first,
+        &mut Tracker::unlimited(),
+        trace,
+    )
+}
+
+fn two_opt_tracked<T: Trace>(
+    starting_tour: &[u16],
+    distance_matrix: &[Vec<i32>],
     preserve_first: bool,
+    tracker: &mut Tracker,
+    trace: &mut T,
 ) -> (Vec<u16>, i32) {
     let mut tour = starting_tour.to_owned();
     let n = tour.len();
-    let mut improvement = true;
+    let mut improvement = n > 2;
+    let mut len = tour_len(&tour, distance_matrix);
+    tracker.evaluated(1);
+    tracker.improved(len);
+    let mut step = 0;
 
-    while improvement {
+    while improvement && !tracker.should_stop() {
         improvement = false;
         let mut min_dist = 0;
         let mut a = 0;
@@ -31,10 +75,7 @@ pub fn two_opt(
 
         for i in start..(n - 1) {
             for j in (i + 1)..n {
-                let distance = distance_matrix[tour[i] as usize][tour[j] as usize]
-                    + distance_matrix[tour[i + 1] as usize][tour[(j + 1) % n] as usize]
-                    - distance_matrix[tour[i] as usize][tour[i + 1] as usize]
-                    - distance_matrix[tour[j] as usize][tour[(j + 1) % n] as usize];
+                let distance = two_opt_delta(&tour, i, j, distance_matrix);
 
                 if distance < min_dist {
                     min_dist = distance;
@@ -45,10 +86,13 @@ pub fn two_opt(
             }
         }
 
+        tracker.iteration();
+        tracker.evaluated(((n - start) * (n - start - 1) / 2) as u64);
         if !improvement {
             break;
         }
 
+        let (i, j) = (a, b);
         //reverse [a+1, b]
         a += 1;
         while a < b {
@@ -56,12 +100,188 @@ pub fn two_opt(
             a += 1;
             b -= 1;
         }
+
+        //the delta assumes a symmetric matrix, the recomputed length is exact
+        let new_len = tour_len(&tour, distance_matrix);
+        if T::ENABLED {
+            step += 1;
+            trace.accepted(Move {
+                kind: MoveKind::TwoOpt,
+                i,
+                j,
+                delta: new_len - len,
+                len: new_len,
+                step,
+            });
+        }
+        len = new_len;
+        tracker.improved(len);
     }
 
-    let len = tour_len(&tour, distance_matrix);
     (tour, len)
 }
 
+/// Change of tour length after reversing positions [i+1, j], assumes a symmetric matrix
+pub fn two_opt_delta(tour: &[u16], i: usize, j: usize, distance_matrix: &[Vec<i32>]) -> i32 {
+    let n = tour.len();
+    distance_matrix[tour[i] as usize][tour[j] as usize]
+        + distance_matrix[tour[i + 1] as usize][tour[(j + 1) % n] as usize]
+        - distance_matrix[tour[i] as usize][tour[i + 1] as usize]
+        - distance_matrix[tour[j] as usize][tour[(j + 1) % n] as usize]
+}
+
+/// First-improvement 2-opt on any tour representation, only trying to connect a city with
+/// its candidate neighbours and skipping cities whose don't-look bit is set.
+/// Candidate lists must be sorted by distance and the matrix symmetric.
+/// Returns the number of applied moves.
+pub fn two_opt_neighbours<T: Tour>(
+    tour: &mut T,
+    distance_matrix: &[Vec<i32>],
+    candidates: &[Vec<u16>],
+) -> u64 {
+    let d = |a: u16, b: u16| distance_matrix[a as usize][b as usize];
+    let mut queue: VecDeque<u16> = (0..tour.len() as u16).collect();
+    let mut queued = vec![true; tour.len()];
+    let mut moves = 0;
+
+    while let Some(a) = queue.pop_front() {
+        queued[a as usize] = false;
+
+        'directions: for forward in [true, false] {
+            let b = match forward {
+                true => tour.next(a),
+                false => tour.prev(a),
+            };
+            for &c in &candidates[a as usize] {
+                //the new edge (a, c) has to be shorter than the removed one (a, b)
+                if d(a, c) >= d(a, b) {
+                    break;
+                }
+                let e = match forward {
+                    true => tour.next(c),
+                    false => tour.prev(c),
+                };
+                if c == b || e == a {
+                    continue;
+                }
+
+                let delta = d(a, c) + d(b, e) - d(a, b) - d(c, e);
+                if delta < 0 {
+                    match forward {
+                        true => tour.flip(a, c),
+                        false => tour.flip(b, e),
+                    }
+                    moves += 1;
+                    for city in [a, b, c, e] {
+                        if !queued[city as usize] {
+                            queued[city as usize] = true;
+                            queue.push_back(city);
+                        }
+                    }
+                    break 'directions;
+                }
+            }
+        }
+    }
+
+    moves
+}
+
+/// Best-improvement 2-opt local search
+#[derive(Debug, Clone, Default)]
+pub struct TwoOpt {
+    pub preserve_first: bool,
+    pub seed: Option<u64>,
+}
+
+impl Solver for TwoOpt {
+    fn name(&self) -> &'static str {
+        "2opt"
+    }
+
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError> {
+        match parameter {
+            "preserve_first" => self.preserve_first = parse_param(parameter, value)?,
+            "seed" => self.seed = Some(parse_param(parameter, value)?),
+            _ => return Err(ConfigError::UnknownParameter(parameter.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution {
+        let mut tracker = Tracker::new(budget, cancel, on_improvement);
+        let starting_tour = match initial {
+            Some(tour) => tour.to_vec(),
+            None => random_solution(distance_matrix.len() as u16, self.seed, self.preserve_first),
+        };
+
+        let (tour, len) = two_opt_tracked(
+            &starting_tour,
+            distance_matrix,
+            self.preserve_first,
+            &mut tracker,
+            &mut (),
+        );
+        tracker.solution(tour, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        helpers::tour_repr::{ArrayTour, SplayTour, TwoLevelTour},
+        test_utils::circle_matrix,
+    };
+
+    #[test]
+    fn neighbour_two_opt_untangles_circle() {
+        let n = 40;
+        let matrix = circle_matrix(n);
+        let optimum = tour_len(&(0..n as u16).collect::<Vec<u16>>(), &matrix);
+        let candidates = candidate_lists(&matrix, n - 1);
+        let start = random_solution(n as u16, Some(9), false);
+
+        let mut array = ArrayTour::new(&start);
+        let mut two_level = TwoLevelTour::new(&start);
+        let mut splay = SplayTour::new(&start);
+        two_opt_neighbours(&mut array, &matrix, &candidates);
+        two_opt_neighbours(&mut two_level, &matrix, &candidates);
+        two_opt_neighbours(&mut splay, &matrix, &candidates);
+
+        assert_eq!(tour_len(&array.to_vec(), &matrix), optimum);
+        assert_eq!(tour_len(&two_level.to_vec(), &matrix), optimum);
+        assert_eq!(tour_len(&splay.to_vec(), &matrix), optimum);
+    }
+
+    #[test]
+    fn trace_records_the_reversals() {
+        let matrix = circle_matrix(12);
+        let start = random_solution(12, Some(5), false);
+        let mut moves = vec![];
+        let traced = two_opt_traced(&start, &matrix, false, &mut moves);
+        assert_eq!(traced, two_opt(&start, &matrix, false));
+        assert!(!moves.is_empty());
+
+        let mut tour = start.clone();
+        for (k, step) in moves.iter().enumerate() {
+            assert_eq!(step.kind, MoveKind::TwoOpt);
+            assert_eq!(step.step, k as u64 + 1);
+            assert_eq!(two_opt_delta(&tour, step.i, step.j, &matrix), step.delta);
+            tour[step.i + 1..=step.j].reverse();
+            assert_eq!(tour_len(&tour, &matrix), step.len);
+        }
+        assert_eq!(moves.last().map(|step| step.len), Some(traced.1));
+    }
+}
+
 #[test]
 fn rev_test() {
     let mut a = 2;
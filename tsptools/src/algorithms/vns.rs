@@ -0,0 +1,620 @@
+use std::str::FromStr;
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    algorithms::{hillclimb::swap_delta, two_opt::two_opt_delta},
+    helpers::{random_solution, tour_len},
+    solver::{
+        parse_param, Budget, CancelToken, ConfigError, Improvement, Solution, Solver, Tracker,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Neighbourhood {
+    /// Exchange of two cities, as in `hillclimb`
+    Swap,
+    /// Reversal of a tour segment, as in `two_opt`
+    TwoOpt,
+    /// Relocation of a single city
+    Insertion,
+    /// Relocation of a segment of two or three cities
+    OrOpt,
+}
+
+impl Neighbourhood {
+    pub const ALL: [Neighbourhood; 4] = [
+        Neighbourhood::Swap,
+        Neighbourhood::TwoOpt,
+        Neighbourhood::Insertion,
+        Neighbourhood::OrOpt,
+    ];
+}
+
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "swap" => Ok(Self::Swap),
+            "2opt" => Ok(Self::TwoOpt),
+            "insertion" => Ok(Self::Insertion),
+            "oropt" => Ok(Self::OrOpt),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Move {
+    Swap(usize, usize),
+    /// Reverse positions [i+1, j]
+    TwoOpt(usize, usize),
+    /// Move `len` cities starting at `start` so that they follow the city at position `after`
+    Relocate {
+        start: usize,
+        len: usize,
+        after: usize,
+    },
+}
+
+/// A single improving move accepted by the VND
+#[derive(Debug, Clone)]
+pub struct VndStep {
+    pub step: usize,
+    pub neighbourhood: Neighbourhood,
+    /// Neighbourhoods in which the tour was a local optimum before this move
+    pub escaped: Vec<Neighbourhood>,
+    pub delta: i32,
+    pub tour_len: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct VndResult {
+    pub tour: Vec<u16>,
+    pub len: i32,
+    pub steps: Vec<VndStep>,
+}
+
+impl VndResult {
+    /// Number of improvements found in given neighbourhood
+    pub fn improvements_in(&self, neighbourhood: Neighbourhood) -> usize {
+        self.steps
+            .iter()
+            .filter(|s| s.neighbourhood == neighbourhood)
+            .count()
+    }
+}
+
+/// Variable Neighbourhood Descent: best-improvement search in the first neighbourhood,
+/// falling back to the next one whenever the current tour is a local optimum.
+pub fn vnd(
+    starting_tour: &[u16],
+    distance_matrix: &[Vec<i32>],
+    neighbourhoods: &[Neighbourhood],
+    preserve_first: bool,
+) -> VndResult {
+    vnd_tracked(
+        starting_tour,
+        distance_matrix,
+        neighbourhoods,
+        preserve_first,
+        &mut Tracker::unlimited(),
+    )
+}
+
+fn vnd_tracked(
+    starting_tour: &[u16],
+    distance_matrix: &[Vec<i32>],
+    neighbourhoods: &[Neighbourhood],
+    preserve_first: bool,
+    tracker: &mut Tracker,
+) -> VndResult {
+    let mut tour = starting_tour.to_vec();
+    let mut len = tour_len(&tour, distance_matrix);
+    let mut steps = vec![];
+    tracker.evaluated(1);
+    tracker.improved(len);
+
+    let mut k = 0;
+    while k < neighbourhoods.len() && !tracker.should_stop() {
+        let neighbourhood = neighbourhoods[k];
+        let (best, evaluated) = best_move(&tour, distance_matrix, neighbourhood, preserve_first);
+        tracker.iteration();
+        tracker.evaluated(evaluated);
+
+        let improved = match best {
+            Some((mv, delta)) if delta < 0 => {
+                let previous = tour.clone();
+                apply_move(&mut tour, mv);
+                let new_len = tour_len(&tour, distance_matrix);
+                //2-opt deltas assume symmetry, so verify the move on the real tour length
+                if new_len < len {
+                    steps.push(VndStep {
+                        step: steps.len(),
+                        neighbourhood,
+                        escaped: neighbourhoods[..k].to_vec(),
+                        delta: new_len - len,
+                        tour_len: new_len,
+                    });
+                    len = new_len;
+                    true
+                } else {
+                    tour = previous;
+                    false
+                }
+            }
+            _ => false,
+        };
+
+        if improved {
+            tracker.improved(len);
+            k = 0;
+        } else {
+            k += 1;
+        }
+    }
+
+    VndResult { tour, len, steps }
+}
+
+fn first_position(preserve_first: bool) -> usize {
+    match preserve_first {
+        true => 1,
+        false => 0,
+    }
+}
+
+/// Returns the best move of the neighbourhood with its delta and the number of moves evaluated
+fn best_move(
+    tour: &[u16],
+    distance_matrix: &[Vec<i32>],
+    neighbourhood: Neighbourhood,
+    preserve_first: bool,
+) -> (Option<(Move, i32)>, u64) {
+    let n = tour.len();
+    let start = first_position(preserve_first);
+    let mut best: Option<(Move, i32)> = None;
+    let mut evaluated = 0;
+    let mut consider = |mv: Move, delta: i32| {
+        evaluated += 1;
+        if best.is_none_or(|(_, d)| delta < d) {
+            best = Some((mv, delta));
+        }
+    };
+
+    match neighbourhood {
+        Neighbourhood::Swap => {
+            for i in start..n {
+                for j in i + 1..n {
+                    consider(Move::Swap(i, j), swap_delta(tour, i, j, distance_matrix));
+                }
+            }
+        }
+        Neighbourhood::TwoOpt => {
+            for i in start..n.saturating_sub(1) {
+                for j in i + 1..n {
+                    consider(
+                        Move::TwoOpt(i, j),
+                        two_opt_delta(tour, i, j, distance_matrix),
+                    );
+                }
+            }
+        }
+        Neighbourhood::Insertion | Neighbourhood::OrOpt => {
+            let lengths = match neighbourhood {
+                Neighbourhood::Insertion => 1..=1,
+                _ => 2..=3,
+            };
+            for len in lengths {
+                for s in start.max(1)..n.saturating_sub(len - 1) {
+                    let e = s + len - 1;
+                    for after in start..n {
+                        if after + 1 >= s && after <= e {
+                            continue;
+                        }
+                        let mv = Move::Relocate {
+                            start: s,
+                            len,
+                            after,
+                        };
+                        consider(mv, relocate_delta(tour, s, e, after, distance_matrix));
+                    }
+                }
+            }
+        }
+    }
+
+    (best, evaluated)
+}
+
+fn relocate_delta(
+    tour: &[u16],
+    s: usize,
+    e: usize,
+    after: usize,
+    distance_matrix: &[Vec<i32>],
+) -> i32 {
+    let n = tour.len();
+    let d = |a: usize, b: usize| distance_matrix[tour[a] as usize][tour[b] as usize];
+    let p = s - 1;
+    let q = (e + 1) % n;
+    let next = (after + 1) % n;
+
+    d(p, q) - d(p, s) - d(e, q) + d(after, s) + d(e, next) - d(after, next)
+}
+
+fn apply_move(tour: &mut Vec<u16>, mv: Move) {
+    match mv {
+        Move::Swap(i, j) => tour.swap(i, j),
+        Move::TwoOpt(i, j) => tour[i + 1..=j].reverse(),
+        Move::Relocate { start, len, after } => {
+            let segment: Vec<u16> = tour.drain(start..start + len).collect();
+            let insert_at = match after > start {
+                true => after + 1 - len,
+                false => after + 1,
+            };
+            tour.splice(insert_at..insert_at, segment);
+        }
+    }
+}
+
+fn random_move<R: Rng>(
+    tour: &[u16],
+    neighbourhood: Neighbourhood,
+    preserve_first: bool,
+    rng: &mut R,
+) -> Option<Move> {
+    let n = tour.len();
+    let start = first_position(preserve_first);
+    if n < start + 3 {
+        return None;
+    }
+
+    let mv = match neighbourhood {
+        Neighbourhood::Swap => {
+            let i = rng.gen_range(start..n - 1);
+            Move::Swap(i, rng.gen_range(i + 1..n))
+        }
+        Neighbourhood::TwoOpt => {
+            let i = rng.gen_range(start..n - 2);
+            Move::TwoOpt(i, rng.gen_range(i + 2..n))
+        }
+        Neighbourhood::Insertion | Neighbourhood::OrOpt => {
+            let len = match neighbourhood {
+                Neighbourhood::Insertion => 1,
+                _ => rng.gen_range(2..=3).min(n - start.max(1) - 1),
+            };
+            let s = rng.gen_range(start.max(1)..n - len + 1);
+            let e = s + len - 1;
+            let targets: Vec<usize> = (start..n).filter(|&a| a + 1 < s || a > e).collect();
+            let after = *targets.choose(rng)?;
+            Move::Relocate {
+                start: s,
+                len,
+                after,
+            }
+        }
+    };
+    Some(mv)
+}
+
+#[derive(Debug, Clone)]
+pub struct VnsConfig {
+    /// Neighbourhoods used by the VND, in order of exploration
+    pub neighbourhoods: Vec<Neighbourhood>,
+    /// Largest number of random moves applied when shaking
+    pub k_max: usize,
+    pub iterations: usize,
+    pub preserve_first: bool,
+    pub seed: Option<u64>,
+}
+
+impl Default for VnsConfig {
+    fn default() -> Self {
+        Self {
+            neighbourhoods: vec![
+                Neighbourhood::TwoOpt,
+                Neighbourhood::Insertion,
+                Neighbourhood::OrOpt,
+                Neighbourhood::Swap,
+            ],
+            k_max: 5,
+            iterations: 100,
+            preserve_first: false,
+            seed: None,
+        }
+    }
+}
+
+/// General Variable Neighbourhood Search: shaking with k random moves followed by VND,
+/// k grows while shaking fails to lead to a better tour.
+pub fn vns(distance_matrix: &[Vec<i32>], config: &VnsConfig) -> (Vec<u16>, i32) {
+    vns_tracked(distance_matrix, config, None, &mut Tracker::unlimited())
+}
+
+fn vns_tracked(
+    distance_matrix: &[Vec<i32>],
+    config: &VnsConfig,
+    initial: Option<&[u16]>,
+    tracker: &mut Tracker,
+) -> (Vec<u16>, i32) {
+    let mut rng = match config.seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+    let starting_tour = match initial {
+        Some(tour) => tour.to_vec(),
+        None => random_solution(
+            distance_matrix.len() as u16,
+            Some(rng.gen()),
+            config.preserve_first,
+        ),
+    };
+
+    let descent = vnd_tracked(
+        &starting_tour,
+        distance_matrix,
+        &config.neighbourhoods,
+        config.preserve_first,
+        tracker,
+    );
+    let (mut best, mut best_len) = (descent.tour, descent.len);
+
+    let mut k = 1;
+    for _ in 0..config.iterations {
+        if tracker.should_stop() || config.neighbourhoods.is_empty() {
+            break;
+        }
+
+        let mut shaken = best.clone();
+        for _ in 0..k {
+            let neighbourhood = *config.neighbourhoods.choose(&mut rng).unwrap();
+            if let Some(mv) = random_move(&shaken, neighbourhood, config.preserve_first, &mut rng) {
+                apply_move(&mut shaken, mv);
+            }
+        }
+
+        tracker.iteration();
+        let descent = vnd_tracked(
+            &shaken,
+            distance_matrix,
+            &config.neighbourhoods,
+            config.preserve_first,
+            tracker,
+        );
+
+        if descent.len < best_len {
+            best = descent.tour;
+            best_len = descent.len;
+            tracker.improved(best_len);
+            k = 1;
+        } else {
+            k = k % config.k_max.max(1) + 1;
+        }
+    }
+
+    (best, best_len)
+}
+
+fn parse_neighbourhoods(parameter: &str, value: &str) -> Result<Vec<Neighbourhood>, ConfigError> {
+    value
+        .split(',')
+        .map(|v| parse_param(parameter, v.trim()))
+        .collect()
+}
+
+/// VND as a standalone local search
+#[derive(Debug, Clone)]
+pub struct Vnd {
+    pub neighbourhoods: Vec<Neighbourhood>,
+    pub preserve_first: bool,
+    pub seed: Option<u64>,
+    /// Improvements of the last run
+    pub steps: Vec<VndStep>,
+}
+
+impl Default for Vnd {
+    fn default() -> Self {
+        Self {
+            neighbourhoods: VnsConfig::default().neighbourhoods,
+            preserve_first: false,
+            seed: None,
+            steps: vec![],
+        }
+    }
+}
+
+impl Solver for Vnd {
+    fn name(&self) -> &'static str {
+        "vnd"
+    }
+
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError> {
+        match parameter {
+            "neighbourhoods" => self.neighbourhoods = parse_neighbourhoods(parameter, value)?,
+            "preserve_first" => self.preserve_first = parse_param(parameter, value)?,
+            "seed" => self.seed = Some(parse_param(parameter, value)?),
+            _ => return Err(ConfigError::UnknownParameter(parameter.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution {
+        let mut tracker = Tracker::new(budget, cancel, on_improvement);
+        let starting_tour = match initial {
+            Some(tour) => tour.to_vec(),
+            None => random_solution(distance_matrix.len() as u16, self.seed, self.preserve_first),
+        };
+
+        let result = vnd_tracked(
+            &starting_tour,
+            distance_matrix,
+            &self.neighbourhoods,
+            self.preserve_first,
+            &mut tracker,
+        );
+        self.steps = result.steps;
+        tracker.solution(result.tour, result.len)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Vns {
+    pub config: VnsConfig,
+}
+
+impl Solver for Vns {
+    fn name(&self) -> &'static str {
+        "vns"
+    }
+
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError> {
+        let config = &mut self.config;
+        match parameter {
+            "neighbourhoods" => config.neighbourhoods = parse_neighbourhoods(parameter, value)?,
+            "k_max" => config.k_max = parse_param(parameter, value)?,
+            "iterations" => config.iterations = parse_param(parameter, value)?,
+            "preserve_first" => config.preserve_first = parse_param(parameter, value)?,
+            "seed" => config.seed = Some(parse_param(parameter, value)?),
+            _ => return Err(ConfigError::UnknownParameter(parameter.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution {
+        let mut tracker = Tracker::new(budget, cancel, on_improvement);
+        let (tour, len) = vns_tracked(distance_matrix, &self.config, initial, &mut tracker);
+        tracker.solution(tour, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        solver::StopReason,
+        test_utils::{circle_matrix, is_permutation},
+    };
+
+    #[test]
+    fn move_deltas_match_tour_length() {
+        let matrix = circle_matrix(9);
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for neighbourhood in Neighbourhood::ALL {
+            for preserve_first in [false, true] {
+                let tour = random_solution(9, Some(rng.gen()), preserve_first);
+                let (best, _) = best_move(&tour, &matrix, neighbourhood, preserve_first);
+                let (mv, delta) = best.unwrap();
+
+                let mut moved = tour.clone();
+                apply_move(&mut moved, mv);
+                assert!(is_permutation(&moved, 9));
+                assert_eq!(
+                    tour_len(&moved, &matrix) - tour_len(&tour, &matrix),
+                    delta,
+                    "{:?}",
+                    mv
+                );
+                if preserve_first {
+                    assert_eq!(moved[0], 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vnd_records_escaping_neighbourhood() {
+        let matrix = circle_matrix(12);
+        let tour = random_solution(12, Some(4), true);
+        let result = vnd(
+            &tour,
+            &matrix,
+            &[Neighbourhood::Swap, Neighbourhood::TwoOpt],
+            true,
+        );
+
+        assert_eq!(result.len, tour_len(&result.tour, &matrix));
+        for step in result
+            .steps
+            .iter()
+            .filter(|s| s.neighbourhood == Neighbourhood::TwoOpt)
+        {
+            assert_eq!(step.escaped, vec![Neighbourhood::Swap]);
+        }
+        assert_eq!(
+            result.improvements_in(Neighbourhood::Swap)
+                + result.improvements_in(Neighbourhood::TwoOpt),
+            result.steps.len()
+        );
+    }
+
+    #[test]
+    fn vns_finds_circle_optimum() {
+        let matrix = circle_matrix(14);
+        let optimum = tour_len(&(0..14).collect::<Vec<u16>>(), &matrix);
+        let config = VnsConfig {
+            preserve_first: true,
+            seed: Some(9),
+            ..Default::default()
+        };
+        let (tour, len) = vns(&matrix, &config);
+        assert_eq!(tour[0], 0);
+        assert_eq!(len, optimum);
+    }
+
+    #[test]
+    fn vns_respects_cancel_and_budget() {
+        let matrix = circle_matrix(60);
+        let start = random_solution(60, Some(3), true);
+        let mut solver = Vns {
+            config: VnsConfig {
+                iterations: 1_000_000,
+                preserve_first: true,
+                seed: Some(1),
+                ..Default::default()
+            },
+        };
+
+        //cancelled when the starting tour is reported, before the first descent moves
+        let cancel = CancelToken::new();
+        let solution = solver.solve(
+            &matrix,
+            Some(&start),
+            &Budget::unlimited(),
+            &cancel,
+            &mut |_| cancel.cancel(),
+        );
+        assert_eq!(solution.stop_reason, StopReason::Cancelled);
+        assert_eq!(solution.tour, start);
+        assert_eq!(solution.len, tour_len(&start, &matrix));
+
+        let solution = solver.solve(
+            &matrix,
+            Some(&start),
+            &Budget::unlimited().with_evaluations(5000),
+            &CancelToken::new(),
+            &mut |_| {},
+        );
+        assert_eq!(solution.stop_reason, StopReason::EvaluationLimit);
+        //one neighbourhood may be explored past the limit, or-opt is the largest
+        assert!(solution.evaluations >= 5000 && solution.evaluations < 5000 + 2 * 60 * 60);
+        assert!(is_permutation(&solution.tour, 60));
+        assert_eq!(solution.len, tour_len(&solution.tour, &matrix));
+    }
+}
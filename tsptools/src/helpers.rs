@@ -3,6 +3,8 @@ use std::{error::Error, fmt::Display};
 use rand::{distributions::Uniform, prelude::Distribution, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 
+pub mod tour_repr;
+
 #[derive(Debug, Clone)]
 pub struct Node {
     pub pos: usize,
@@ -32,7 +34,7 @@ impl Display for ParsingError {
 impl Error for ParsingError {}
 
 pub fn generate_distance_matrix(
-    nodes: &Vec<Node>,
+    nodes: &[Node],
     weight_type: &str,
 ) -> Result<Vec<Vec<i32>>, Box<dyn Error>> {
     let mut matrix = vec![vec![0; nodes.len()]; nodes.len()];
@@ -93,6 +95,8 @@ pub fn dist_geo(n1: &Node, n2: &Node) -> i32 {
     (rrr * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0) as i32
 }
 
+//TSPLIB defines PI with exactly this precision for GEO distances
+#[allow(clippy::approx_constant)]
 pub fn to_rad(x: f32) -> f32 {
     let pi = 3.141592;
     let deg = x as i32;
@@ -101,7 +105,10 @@ pub fn to_rad(x: f32) -> f32 {
     pi * (deg as f32 + 5.0 * min / 3.0) / 180.0
 }
 
-pub fn tour_len(path: &Vec<u16>, distance_matrix: &Vec<Vec<i32>>) -> i32 {
+pub fn tour_len(path: &[u16], distance_matrix: &[Vec<i32>]) -> i32 {
+    if path.is_empty() {
+        return 0;
+    }
     let len: i32 = path.windows(2).map(|w| distance_matrix[w[0] as usize][w[1] as usize]).sum();
     len + distance_matrix[path[0] as usize][path[path.len() - 1] as usize]
 }
@@ -128,8 +135,47 @@ pub fn random_solution(node_count: u16, seed: Option<u64>, preserve_first: bool)
     path
 }
 
+pub fn nearest_neighbor_tour(distance_matrix: &[Vec<i32>], start: u16) -> Vec<u16> {
+    let n = distance_matrix.len();
+    let mut visited = vec![false; n];
+    let mut tour = Vec::with_capacity(n);
+    let mut current = start as usize;
+
+    for _ in 0..n {
+        visited[current] = true;
+        tour.push(current as u16);
+        let next = (0..n)
+            .filter(|&j| !visited[j])
+            .min_by_key(|&j| distance_matrix[current][j]);
+        match next {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+
+    tour
+}
+
+/// For every city returns up to `k` other cities sorted by increasing distance
+pub fn candidate_lists(distance_matrix: &[Vec<i32>], k: usize) -> Vec<Vec<u16>> {
+    let n = distance_matrix.len();
+    (0..n)
+        .map(|i| {
+            let mut others: Vec<u16> = (0..n as u16).filter(|&j| j as usize != i).collect();
+            others.sort_by_key(|&j| distance_matrix[i][j as usize]);
+            others.truncate(k);
+            others
+        })
+        .collect()
+}
+
+pub fn is_symmetric(distance_matrix: &[Vec<i32>]) -> bool {
+    let n = distance_matrix.len();
+    (0..n).all(|i| (i + 1..n).all(|j| distance_matrix[i][j] == distance_matrix[j][i]))
+}
+
 pub fn nodes_to_ids(path: &[Node]) -> Vec<usize> {
-    return path.iter().map(|node| node.pos).collect();
+    path.iter().map(|node| node.pos).collect()
 }
 
 pub fn cmp_permutations(perm1: &[u16], perm2: &[u16]) -> u32 {
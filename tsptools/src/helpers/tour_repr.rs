@@ -0,0 +1,711 @@
+//! Tour representations with cheap 2-opt moves for large instances.
+//!
+//! A flip may reverse either side of the cycle, so the orientation of a tour can change
+//! after any move; `next`, `prev` and `between` always refer to the current orientation.
+//! Queries take `&mut self` so that self-adjusting representations can restructure on access.
+
+const NIL: usize = usize::MAX;
+
+/// Cyclic tour supporting the queries and moves needed by 2-opt, 3-opt and Lin-Kernighan
+pub trait Tour {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn next(&mut self, city: u16) -> u16;
+
+    fn prev(&mut self, city: u16) -> u16;
+
+    /// True if b lies on the path from a to c following `next`, endpoints included
+    fn between(&mut self, a: u16, b: u16, c: u16) -> bool;
+
+    /// Replaces the edges (a, next(a)) and (c, next(c)) by (a, c) and (next(a), next(c))
+    /// by reversing the path next(a)..c or its complement
+    fn flip(&mut self, a: u16, c: u16);
+
+    /// Cities in tour order starting with city 0
+    fn to_vec(&mut self) -> Vec<u16> {
+        let mut tour = Vec::with_capacity(self.len());
+        if self.is_empty() {
+            return tour;
+        }
+        let mut city = 0;
+        for _ in 0..self.len() {
+            tour.push(city);
+            city = self.next(city);
+        }
+        tour
+    }
+}
+
+/// True if pb lies on the cyclic range pa..=pc of positions
+fn between_positions(pa: usize, pb: usize, pc: usize) -> bool {
+    match pa <= pc {
+        true => pa <= pb && pb <= pc,
+        false => pb >= pa || pb <= pc,
+    }
+}
+
+/// Plain array with a position index, flips reverse the shorter side of the cycle in O(n)
+#[derive(Debug, Clone)]
+pub struct ArrayTour {
+    tour: Vec<u16>,
+    pos: Vec<usize>,
+}
+
+impl ArrayTour {
+    pub fn new(tour: &[u16]) -> Self {
+        let mut pos = vec![0; tour.len()];
+        for (i, &city) in tour.iter().enumerate() {
+            pos[city as usize] = i;
+        }
+        Self {
+            tour: tour.to_vec(),
+            pos,
+        }
+    }
+
+    /// Reverses the cyclic range of positions from..=to
+    fn reverse(&mut self, from: usize, to: usize) {
+        let n = self.tour.len();
+        let (mut i, mut j) = (from, to);
+        for _ in 0..((to + n - from) % n).div_ceil(2) {
+            self.tour.swap(i, j);
+            self.pos[self.tour[i] as usize] = i;
+            self.pos[self.tour[j] as usize] = j;
+            i = (i + 1) % n;
+            j = (j + n - 1) % n;
+        }
+    }
+}
+
+impl Tour for ArrayTour {
+    fn len(&self) -> usize {
+        self.tour.len()
+    }
+
+    fn next(&mut self, city: u16) -> u16 {
+        self.tour[(self.pos[city as usize] + 1) % self.tour.len()]
+    }
+
+    fn prev(&mut self, city: u16) -> u16 {
+        let n = self.tour.len();
+        self.tour[(self.pos[city as usize] + n - 1) % n]
+    }
+
+    fn between(&mut self, a: u16, b: u16, c: u16) -> bool {
+        between_positions(
+            self.pos[a as usize],
+            self.pos[b as usize],
+            self.pos[c as usize],
+        )
+    }
+
+    fn flip(&mut self, a: u16, c: u16) {
+        let n = self.tour.len();
+        let (b, d) = (self.next(a), self.next(c));
+        let (pb, pc) = (self.pos[b as usize], self.pos[c as usize]);
+
+        let inner = (pc + n - pb) % n + 1;
+        match inner * 2 <= n {
+            true => self.reverse(pb, pc),
+            false => self.reverse(self.pos[d as usize], self.pos[a as usize]),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    cities: Vec<u16>,
+    reversed: bool,
+    rank: usize,
+}
+
+/// Two-level list: the tour is split into about sqrt(n) segments with a reversal bit each.
+/// Flips split segments at the ends of the path and reverse the order of whole segments,
+/// the list is rebuilt once splitting has produced too many segments.
+#[derive(Debug, Clone)]
+pub struct TwoLevelTour {
+    segments: Vec<Segment>,
+    /// Segment ids in tour order
+    order: Vec<usize>,
+    segment_of: Vec<usize>,
+    /// Index of a city in the cities of its segment
+    index: Vec<usize>,
+    max_segments: usize,
+}
+
+impl TwoLevelTour {
+    pub fn new(tour: &[u16]) -> Self {
+        let mut two_level = Self {
+            segments: vec![],
+            order: vec![],
+            segment_of: vec![0; tour.len()],
+            index: vec![0; tour.len()],
+            max_segments: 0,
+        };
+        two_level.rebuild(tour);
+        two_level
+    }
+
+    fn rebuild(&mut self, tour: &[u16]) {
+        let size = ((tour.len() as f64).sqrt().ceil() as usize).max(1);
+        self.segments = tour
+            .chunks(size)
+            .enumerate()
+            .map(|(rank, chunk)| Segment {
+                cities: chunk.to_vec(),
+                reversed: false,
+                rank,
+            })
+            .collect();
+        self.order = (0..self.segments.len()).collect();
+        self.max_segments = 3 * self.segments.len() + 4;
+
+        for (s, segment) in self.segments.iter().enumerate() {
+            for (i, &city) in segment.cities.iter().enumerate() {
+                self.segment_of[city as usize] = s;
+                self.index[city as usize] = i;
+            }
+        }
+    }
+
+    /// Position of a city inside its segment following the tour orientation
+    fn oriented_index(&self, city: u16) -> usize {
+        let segment = &self.segments[self.segment_of[city as usize]];
+        match segment.reversed {
+            true => segment.cities.len() - 1 - self.index[city as usize],
+            false => self.index[city as usize],
+        }
+    }
+
+    fn first(&self, s: usize) -> u16 {
+        let segment = &self.segments[s];
+        match segment.reversed {
+            true => *segment.cities.last().unwrap(),
+            false => segment.cities[0],
+        }
+    }
+
+    fn last(&self, s: usize) -> u16 {
+        let segment = &self.segments[s];
+        match segment.reversed {
+            true => segment.cities[0],
+            false => *segment.cities.last().unwrap(),
+        }
+    }
+
+    /// Splits the segment of city so that city becomes the first one of a segment
+    fn split_before(&mut self, city: u16) {
+        let s = self.segment_of[city as usize];
+        let i = self.oriented_index(city);
+        if i == 0 {
+            return;
+        }
+
+        //the part after city in tour order moves into a new segment placed after s
+        let len = self.segments[s].cities.len();
+        let segment = &mut self.segments[s];
+        let moved = match segment.reversed {
+            true => {
+                let tail = segment.cities.split_off(len - i);
+                std::mem::replace(&mut segment.cities, tail)
+            }
+            false => segment.cities.split_off(i),
+        };
+        let reversed = segment.reversed;
+        let rank = segment.rank;
+
+        let new = self.segments.len();
+        for (k, &c) in moved.iter().enumerate() {
+            self.segment_of[c as usize] = new;
+            self.index[c as usize] = k;
+        }
+        for (k, &c) in self.segments[s].cities.iter().enumerate() {
+            self.index[c as usize] = k;
+        }
+        self.segments.push(Segment {
+            cities: moved,
+            reversed,
+            rank: rank + 1,
+        });
+        self.order.insert(rank + 1, new);
+        for r in rank + 2..self.order.len() {
+            self.segments[self.order[r]].rank = r;
+        }
+    }
+
+    /// Reverses the cyclic range of segment ranks from..=to
+    fn reverse_segments(&mut self, from: usize, to: usize) {
+        let m = self.order.len();
+        let count = (to + m - from) % m + 1;
+        let (mut i, mut j) = (from, to);
+        for _ in 0..count / 2 {
+            self.order.swap(i, j);
+            i = (i + 1) % m;
+            j = (j + m - 1) % m;
+        }
+        for k in 0..count {
+            let r = (from + k) % m;
+            let segment = &mut self.segments[self.order[r]];
+            segment.reversed = !segment.reversed;
+            segment.rank = r;
+        }
+    }
+}
+
+impl Tour for TwoLevelTour {
+    fn len(&self) -> usize {
+        self.segment_of.len()
+    }
+
+    fn next(&mut self, city: u16) -> u16 {
+        let s = self.segment_of[city as usize];
+        if city != self.last(s) {
+            let segment = &self.segments[s];
+            let i = self.index[city as usize];
+            return match segment.reversed {
+                true => segment.cities[i - 1],
+                false => segment.cities[i + 1],
+            };
+        }
+        let rank = (self.segments[s].rank + 1) % self.order.len();
+        self.first(self.order[rank])
+    }
+
+    fn prev(&mut self, city: u16) -> u16 {
+        let s = self.segment_of[city as usize];
+        if city != self.first(s) {
+            let segment = &self.segments[s];
+            let i = self.index[city as usize];
+            return match segment.reversed {
+                true => segment.cities[i + 1],
+                false => segment.cities[i - 1],
+            };
+        }
+        let m = self.order.len();
+        let rank = (self.segments[s].rank + m - 1) % m;
+        self.last(self.order[rank])
+    }
+
+    fn between(&mut self, a: u16, b: u16, c: u16) -> bool {
+        let key = |city: u16| {
+            let rank = self.segments[self.segment_of[city as usize]].rank;
+            (rank, self.oriented_index(city))
+        };
+        let (ka, kb, kc) = (key(a), key(b), key(c));
+        match ka <= kc {
+            true => ka <= kb && kb <= kc,
+            false => kb >= ka || kb <= kc,
+        }
+    }
+
+    fn flip(&mut self, a: u16, c: u16) {
+        let (b, d) = (self.next(a), self.next(c));
+        if b == c || a == c {
+            return;
+        }
+        self.split_before(b);
+        self.split_before(d);
+
+        let rank = |t: &Self, city: u16| t.segments[t.segment_of[city as usize]].rank;
+        let m = self.order.len();
+        let (rb, rc) = (rank(self, b), rank(self, c));
+        match ((rc + m - rb) % m + 1) * 2 <= m {
+            true => self.reverse_segments(rb, rc),
+            false => self.reverse_segments(rank(self, d), rank(self, a)),
+        }
+
+        if self.order.len() > self.max_segments {
+            let tour = self.to_vec();
+            self.rebuild(&tour);
+        }
+    }
+}
+
+/// Splay tree keyed implicitly by tour position with lazy reversal flags.
+/// Every query splays the city it looks up, so flips and queries take amortized O(log n).
+#[derive(Debug, Clone)]
+pub struct SplayTour {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    reversed: Vec<bool>,
+    root: usize,
+    //ancestors of the city being splayed, kept to avoid allocating on every query
+    path: Vec<usize>,
+}
+
+impl SplayTour {
+    pub fn new(tour: &[u16]) -> Self {
+        let n = tour.len();
+        let mut splay = Self {
+            left: vec![NIL; n],
+            right: vec![NIL; n],
+            parent: vec![NIL; n],
+            size: vec![1; n],
+            reversed: vec![false; n],
+            root: NIL,
+            path: vec![],
+        };
+        splay.root = splay.build(tour, NIL);
+        splay
+    }
+
+    fn build(&mut self, cities: &[u16], parent: usize) -> usize {
+        if cities.is_empty() {
+            return NIL;
+        }
+        let mid = cities.len() / 2;
+        let node = cities[mid] as usize;
+        self.parent[node] = parent;
+        self.left[node] = self.build(&cities[..mid], node);
+        self.right[node] = self.build(&cities[mid + 1..], node);
+        self.update(node);
+        node
+    }
+
+    fn size_of(&self, node: usize) -> usize {
+        match node {
+            NIL => 0,
+            node => self.size[node],
+        }
+    }
+
+    fn update(&mut self, node: usize) {
+        self.size[node] = 1 + self.size_of(self.left[node]) + self.size_of(self.right[node]);
+    }
+
+    fn push(&mut self, node: usize) {
+        if !self.reversed[node] {
+            return;
+        }
+        self.reversed[node] = false;
+        std::mem::swap(&mut self.left[node], &mut self.right[node]);
+        for child in [self.left[node], self.right[node]] {
+            if child != NIL {
+                self.reversed[child] = !self.reversed[child];
+            }
+        }
+    }
+
+    fn rotate(&mut self, x: usize) {
+        let p = self.parent[x];
+        let g = self.parent[p];
+        if self.left[p] == x {
+            self.left[p] = self.right[x];
+            if self.right[x] != NIL {
+                self.parent[self.right[x]] = p;
+            }
+            self.right[x] = p;
+        } else {
+            self.right[p] = self.left[x];
+            if self.left[x] != NIL {
+                self.parent[self.left[x]] = p;
+            }
+            self.left[x] = p;
+        }
+        self.parent[p] = x;
+        self.parent[x] = g;
+        if g != NIL {
+            if self.left[g] == p {
+                self.left[g] = x;
+            } else {
+                self.right[g] = x;
+            }
+        }
+        self.update(p);
+        self.update(x);
+    }
+
+    /// Moves x to the root of its tree, the path to x must already be pushed
+    fn splay(&mut self, x: usize) {
+        while self.parent[x] != NIL {
+            let p = self.parent[x];
+            let g = self.parent[p];
+            if g != NIL {
+                let zig_zig = (self.left[g] == p) == (self.left[p] == x);
+                match zig_zig {
+                    true => self.rotate(p),
+                    false => self.rotate(x),
+                }
+            }
+            self.rotate(x);
+        }
+    }
+
+    /// Splays the k-th node of the tree rooted at root and returns it
+    fn splay_kth(&mut self, root: usize, mut k: usize) -> usize {
+        let mut node = root;
+        loop {
+            self.push(node);
+            let left_size = self.size_of(self.left[node]);
+            match k.cmp(&left_size) {
+                std::cmp::Ordering::Less => node = self.left[node],
+                std::cmp::Ordering::Equal => break,
+                std::cmp::Ordering::Greater => {
+                    k -= left_size + 1;
+                    node = self.right[node];
+                }
+            }
+        }
+        self.splay(node);
+        node
+    }
+
+    /// Splits the tree into the first k positions and the rest
+    fn split(&mut self, root: usize, k: usize) -> (usize, usize) {
+        if k == 0 {
+            return (NIL, root);
+        }
+        if k >= self.size_of(root) {
+            return (root, NIL);
+        }
+        let node = self.splay_kth(root, k - 1);
+        let rest = self.right[node];
+        self.parent[rest] = NIL;
+        self.right[node] = NIL;
+        self.update(node);
+        (node, rest)
+    }
+
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
+        let node = self.splay_kth(a, self.size[a] - 1);
+        self.right[node] = b;
+        self.parent[b] = node;
+        self.update(node);
+        node
+    }
+
+    fn reverse_range(&mut self, from: usize, to: usize) {
+        let (a, rest) = self.split(self.root, from);
+        let (b, c) = self.split(rest, to - from + 1);
+        self.reversed[b] = !self.reversed[b];
+        let tail = self.merge(b, c);
+        self.root = self.merge(a, tail);
+    }
+
+    /// Splays city to the root and returns its position
+    fn rank(&mut self, city: u16) -> usize {
+        let x = city as usize;
+        //pending reversals above x have to be pushed from the root down before rotating
+        let mut node = x;
+        while node != NIL {
+            self.path.push(node);
+            node = self.parent[node];
+        }
+        while let Some(node) = self.path.pop() {
+            self.push(node);
+        }
+        self.splay(x);
+        self.root = x;
+        self.size_of(self.left[x])
+    }
+
+    /// Splays the city at position k to the root and returns it
+    fn kth(&mut self, k: usize) -> u16 {
+        self.root = self.splay_kth(self.root, k);
+        self.root as u16
+    }
+}
+
+impl Tour for SplayTour {
+    fn len(&self) -> usize {
+        self.size.len()
+    }
+
+    fn next(&mut self, city: u16) -> u16 {
+        let rank = self.rank(city);
+        self.kth((rank + 1) % self.len())
+    }
+
+    fn prev(&mut self, city: u16) -> u16 {
+        let n = self.len();
+        let rank = self.rank(city);
+        self.kth((rank + n - 1) % n)
+    }
+
+    fn between(&mut self, a: u16, b: u16, c: u16) -> bool {
+        between_positions(self.rank(a), self.rank(b), self.rank(c))
+    }
+
+    fn flip(&mut self, a: u16, c: u16) {
+        let (b, d) = (self.next(a), self.next(c));
+        if b == c || a == c {
+            return;
+        }
+        let (rb, rc) = (self.rank(b), self.rank(c));
+        match rb <= rc {
+            true => self.reverse_range(rb, rc),
+            false => {
+                let (rd, ra) = (self.rank(d), self.rank(a));
+                self.reverse_range(rd, ra)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::random_solution;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    /// Reference flip on a plain vector, always reversing next(a)..c
+    fn flip_vec(tour: &mut [u16], a: u16, c: u16) {
+        let n = tour.len();
+        let pa = tour.iter().position(|&x| x == a).unwrap();
+        tour.rotate_left((pa + 1) % n);
+        let pc = tour.iter().position(|&x| x == c).unwrap();
+        tour[..=pc].reverse();
+    }
+
+    /// Rotates to city 0 and picks the direction with the smaller second city
+    fn canonical(mut tour: Vec<u16>) -> Vec<u16> {
+        let p = tour.iter().position(|&x| x == 0).unwrap();
+        tour.rotate_left(p);
+        if tour.len() > 2 && tour[1] > tour[tour.len() - 1] {
+            tour[1..].reverse();
+        }
+        tour
+    }
+
+    /// Flips both the representation and a reference built from its current orientation
+    fn flip_and_check<T: Tour>(tour: &mut T, a: u16, c: u16) {
+        let mut expected = tour.to_vec();
+        flip_vec(&mut expected, a, c);
+        tour.flip(a, c);
+
+        let order = tour.to_vec();
+        assert_eq!(canonical(order.clone()), canonical(expected));
+        let n = order.len();
+        for i in 0..n {
+            assert_eq!(tour.next(order[i]), order[(i + 1) % n]);
+            assert_eq!(tour.prev(order[(i + 1) % n]), order[i]);
+        }
+        assert!(tour.between(order[1], order[2], order[n - 1]));
+        assert!(!tour.between(order[2], order[1], order[n - 1]));
+    }
+
+    #[test]
+    fn representations_agree_with_reference() {
+        let n = 50;
+        let start = random_solution(n, Some(2), false);
+        let mut array = ArrayTour::new(&start);
+        let mut two_level = TwoLevelTour::new(&start);
+        let mut splay = SplayTour::new(&start);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        for _ in 0..300 {
+            let a = rng.gen_range(0..n);
+            let c = rng.gen_range(0..n);
+            if a == c {
+                continue;
+            }
+            flip_and_check(&mut array, a, c);
+            flip_and_check(&mut two_level, a, c);
+            flip_and_check(&mut splay, a, c);
+        }
+    }
+
+    #[test]
+    fn splay_flip_matches_array_in_both_branches() {
+        let n = 12;
+        let start: Vec<u16> = (0..n).collect();
+        let mut array = ArrayTour::new(&start);
+        let mut splay = SplayTour::new(&start);
+
+        //(2, 6) reverses the positions of next(2)..6, (8, 3) wraps around and reverses
+        //next(3)..8 instead, then random flips on the tree reshaped by the splaying.
+        //The two may orient the tour differently, so only the cycles are compared.
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let flips: Vec<(u16, u16)> = [(2, 6), (8, 3)]
+            .into_iter()
+            .chain((0..200).map(|_| (rng.gen_range(0..n), rng.gen_range(0..n))))
+            .collect();
+        let mut branches = [0, 0];
+        for (a, c) in flips {
+            let b = splay.next(a);
+            if a == c || b == c {
+                continue;
+            }
+            branches[(splay.rank(b) > splay.rank(c)) as usize] += 1;
+
+            //the same two edges, named from the orientation of the array
+            match array.next(a) == b {
+                true => array.flip(a, c),
+                false => {
+                    let (prev_a, prev_c) = (array.prev(a), array.prev(c));
+                    array.flip(prev_a, prev_c)
+                }
+            }
+            splay.flip(a, c);
+            let order = splay.to_vec();
+            assert_eq!(canonical(order.clone()), canonical(array.to_vec()));
+            //to_vec starts at city 0, wherever it is in the tree
+            let first = splay.rank(0);
+            for i in 0..n as usize {
+                assert_eq!(splay.rank(order[i]), (first + i) % n as usize);
+                assert_eq!(splay.next(order[i]), order[(i + 1) % n as usize]);
+            }
+        }
+        assert!(branches[0] > 0 && branches[1] > 0, "{:?}", branches);
+    }
+
+    /// Nodes on the path from the root to city, what a query has to walk
+    fn depth(tour: &SplayTour, city: u16) -> usize {
+        let mut node = city as usize;
+        let mut depth = 0;
+        while node != NIL {
+            depth += 1;
+            node = tour.parent[node];
+        }
+        depth
+    }
+
+    #[test]
+    fn splay_queries_stay_logarithmic() {
+        let n = 50000;
+        let mut splay = SplayTour::new(&random_solution(n as u16, Some(3), false));
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let (mut queries, mut walked, mut deepest) = (0, 0, 0);
+        let mut query = |splay: &mut SplayTour, city: u16| {
+            let d = depth(splay, city);
+            walked += d;
+            deepest = deepest.max(d);
+            queries += 1;
+            splay.next(city)
+        };
+
+        //a full walk along the tour splays the tree into a long path, single queries may then
+        //be deep but the average over all of them, mixed with flips, stays logarithmic
+        let mut city = 0;
+        for _ in 0..n {
+            city = query(&mut splay, city);
+        }
+        for _ in 0..20000 {
+            let (a, c) = (rng.gen_range(0..n as u16), rng.gen_range(0..n as u16));
+            if a != c {
+                splay.flip(a, c);
+            }
+            for _ in 0..5 {
+                query(&mut splay, rng.gen_range(0..n as u16));
+            }
+        }
+
+        let log_n = (n as f64).log2();
+        let average = walked as f64 / queries as f64;
+        assert!(deepest > 1000, "the walk should have left a deep path");
+        assert!(average < 3.0 * log_n, "{} >= 3 log2 n", average);
+    }
+}
@@ -1,3 +1,10 @@
 pub mod algorithms;
 pub mod helpers;
-pub mod parsers;
\ No newline at end of file
+pub mod parsers;
+pub mod solver;
+pub mod tour;
+pub mod tour_distance;
+pub mod trace;
+
+#[cfg(test)]
+mod test_utils;
@@ -1,5 +1,5 @@
 use crate::helpers::*;
-use std::{error::Error, fmt::Display, fs};
+use std::{error::Error, fs};
 
 #[derive(Debug, Clone)]
 pub struct TspFile {
@@ -9,7 +9,7 @@ pub struct TspFile {
 }
 
 pub fn parse_tsp_file(path: &str) -> Result<TspFile, Box<dyn Error>> {
-    let file = fs::read_to_string(path).unwrap();
+    let file = fs::read_to_string(path)?;
     let lines: Vec<&str> = file.lines().collect();
     let mut dimension = None;
     let mut name = None;
@@ -117,6 +117,7 @@ fn parse_full_matrix(lines: &[&str]) -> Result<Vec<Vec<i32>>, Box<dyn Error>> {
     Ok(d_matrix)
 }
 
+#[allow(dead_code)]
 fn parse_half_matrix(
     lines: &[&str],
     dim: usize,
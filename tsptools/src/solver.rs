@@ -0,0 +1,335 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::algorithms::{
+    aco::AntColony,
+    genetic::Genetic,
+    grasp::Grasp,
+    guided_local_search::GuidedLocalSearch,
+    hillclimb::HillClimb,
+    two_opt::TwoOpt,
+    vns::{Vnd, Vns},
+};
+
+/// Names accepted by [`solver_by_name`]
+pub const SOLVER_NAMES: [&str; 8] = ["hc", "2opt", "vnd", "vns", "gls", "grasp", "ga", "aco"];
+
+/// Common interface of all algorithms, so that harnesses can run any of them interchangeably
+pub trait Solver: Send {
+    fn name(&self) -> &'static str;
+
+    /// Sets a single named parameter, e.g. `("preserve_first", "true")`
+    fn configure(&mut self, parameter: &str, value: &str) -> Result<(), ConfigError>;
+
+    /// Runs the algorithm until it converges, the budget is exhausted or `cancel` is triggered.
+    /// `initial` is the starting tour of local searches and seeds populations of metaheuristics.
+    fn solve(
+        &mut self,
+        distance_matrix: &[Vec<i32>],
+        initial: Option<&[u16]>,
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &mut dyn FnMut(&Improvement),
+    ) -> Solution;
+}
+
+pub fn solver_by_name(name: &str) -> Option<Box<dyn Solver>> {
+    match name {
+        "hc" => Some(Box::<HillClimb>::default()),
+        "2opt" => Some(Box::<TwoOpt>::default()),
+        "gls" => Some(Box::<GuidedLocalSearch>::default()),
+        "grasp" => Some(Box::<Grasp>::default()),
+        "ga" => Some(Box::<Genetic>::default()),
+        "vnd" => Some(Box::<Vnd>::default()),
+        "vns" => Some(Box::<Vns>::default()),
+        "aco" => Some(Box::<AntColony>::default()),
+        _ => None,
+    }
+}
+
+/// Limits on a single run, unset fields are unlimited
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    pub time: Option<Duration>,
+    pub iterations: Option<u64>,
+    pub evaluations: Option<u64>,
+}
+
+impl Budget {
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    pub fn with_time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn with_iterations(mut self, iterations: u64) -> Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    pub fn with_evaluations(mut self, evaluations: u64) -> Self {
+        self.evaluations = Some(evaluations);
+        self
+    }
+}
+
+/// Cloneable flag used to stop a running solver from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Reported every time a solver finds a tour shorter than all previous ones
+#[derive(Debug, Clone)]
+pub struct Improvement {
+    pub tour_len: i32,
+    pub iteration: u64,
+    pub evaluations: u64,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Converged,
+    TimeLimit,
+    IterationLimit,
+    EvaluationLimit,
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub tour: Vec<u16>,
+    pub len: i32,
+    pub iterations: u64,
+    pub evaluations: u64,
+    pub elapsed: Duration,
+    pub stop_reason: StopReason,
+}
+
+/// Bookkeeping shared by solver implementations: counts iterations and evaluations,
+/// checks the budget and forwards improvements to the callback.
+pub struct Tracker<'a> {
+    budget: Budget,
+    cancel: Option<CancelToken>,
+    on_improvement: Option<&'a mut dyn FnMut(&Improvement)>,
+    start: Instant,
+    iterations: u64,
+    evaluations: u64,
+    best_len: Option<i32>,
+    stop_reason: Option<StopReason>,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(
+        budget: &Budget,
+        cancel: &CancelToken,
+        on_improvement: &'a mut dyn FnMut(&Improvement),
+    ) -> Self {
+        Self {
+            budget: budget.clone(),
+            cancel: Some(cancel.clone()),
+            on_improvement: Some(on_improvement),
+            ..Tracker::unlimited()
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self {
+            budget: Budget::unlimited(),
+            cancel: None,
+            on_improvement: None,
+            start: Instant::now(),
+            iterations: 0,
+            evaluations: 0,
+            best_len: None,
+            stop_reason: None,
+        }
+    }
+
+    pub fn iteration(&mut self) {
+        self.iterations += 1;
+    }
+
+    pub fn evaluated(&mut self, count: u64) {
+        self.evaluations += count;
+    }
+
+    pub fn improved(&mut self, tour_len: i32) {
+        if self.best_len.is_some_and(|best| best <= tour_len) {
+            return;
+        }
+        self.best_len = Some(tour_len);
+
+        if let Some(callback) = self.on_improvement.as_mut() {
+            callback(&Improvement {
+                tour_len,
+                iteration: self.iterations,
+                evaluations: self.evaluations,
+                elapsed: self.start.elapsed(),
+            });
+        }
+    }
+
+    /// Returns true once the budget is exhausted or the run was cancelled
+    pub fn should_stop(&mut self) -> bool {
+        if self.stop_reason.is_some() {
+            return true;
+        }
+
+        let reason = if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            Some(StopReason::Cancelled)
+        } else if self
+            .budget
+            .iterations
+            .is_some_and(|max| self.iterations >= max)
+        {
+            Some(StopReason::IterationLimit)
+        } else if self
+            .budget
+            .evaluations
+            .is_some_and(|max| self.evaluations >= max)
+        {
+            Some(StopReason::EvaluationLimit)
+        } else if self
+            .budget
+            .time
+            .is_some_and(|max| self.start.elapsed() >= max)
+        {
+            Some(StopReason::TimeLimit)
+        } else {
+            None
+        };
+
+        self.stop_reason = reason;
+        reason.is_some()
+    }
+
+    pub fn solution(&self, tour: Vec<u16>, len: i32) -> Solution {
+        Solution {
+            tour,
+            len,
+            iterations: self.iterations,
+            evaluations: self.evaluations,
+            elapsed: self.start.elapsed(),
+            stop_reason: self.stop_reason.unwrap_or(StopReason::Converged),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConfigError {
+    UnknownParameter(String),
+    InvalidValue { parameter: String, value: String },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownParameter(parameter) => {
+                write!(f, "Config Error: unknown parameter {}", parameter)
+            }
+            Self::InvalidValue { parameter, value } => write!(
+                f,
+                "Config Error: invalid value {} for parameter {}",
+                value, parameter
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+pub fn parse_param<T: FromStr>(parameter: &str, value: &str) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidValue {
+        parameter: parameter.to_owned(),
+        value: value.to_owned(),
+    })
+}
+
+/// Like [`parse_param`], rejecting values for which `valid` is false
+pub fn parse_checked<T: FromStr + Copy>(
+    parameter: &str,
+    value: &str,
+    valid: impl Fn(T) -> bool,
+) -> Result<T, ConfigError> {
+    parse_param(parameter, value)
+        .ok()
+        .filter(|&parsed| valid(parsed))
+        .ok_or_else(|| ConfigError::InvalidValue {
+            parameter: parameter.to_owned(),
+            value: value.to_owned(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::circle_matrix;
+
+    #[test]
+    fn every_solver_runs_within_budget() {
+        let matrix = circle_matrix(10);
+        for name in SOLVER_NAMES {
+            let mut solver = solver_by_name(name).unwrap();
+            solver.configure("seed", "5").unwrap();
+            solver.configure("preserve_first", "true").unwrap();
+
+            let mut improvements = vec![];
+            let solution = solver.solve(
+                &matrix,
+                None,
+                &Budget::unlimited().with_iterations(5),
+                &CancelToken::new(),
+                &mut |i| improvements.push(i.tour_len),
+            );
+
+            assert_eq!(solution.tour[0], 0, "{}", name);
+            assert!(solution.iterations <= 5, "{}", name);
+            assert_eq!(improvements.last(), Some(&solution.len), "{}", name);
+            assert!(improvements.windows(2).all(|w| w[1] < w[0]), "{}", name);
+        }
+    }
+
+    #[test]
+    fn cancelled_solver_stops() {
+        let matrix = circle_matrix(10);
+        let cancel = CancelToken::new();
+        cancel.cancel();
+
+        let mut solver = solver_by_name("ga").unwrap();
+        let solution = solver.solve(&matrix, None, &Budget::unlimited(), &cancel, &mut |_| {});
+        assert_eq!(solution.stop_reason, StopReason::Cancelled);
+        assert_eq!(solution.iterations, 0);
+    }
+
+    #[test]
+    fn unknown_parameter_is_rejected() {
+        let mut solver = solver_by_name("2opt").unwrap();
+        assert!(solver.configure("population_size", "10").is_err());
+        assert!(solver.configure("preserve_first", "maybe").is_err());
+    }
+}
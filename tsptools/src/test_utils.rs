@@ -0,0 +1,24 @@
+/// Distance matrix of `n` cities evenly spaced on a circle, optimal tour is 0, 1, ..., n-1
+pub fn circle_matrix(n: usize) -> Vec<Vec<i32>> {
+    let points: Vec<(f64, f64)> = (0..n)
+        .map(|i| {
+            let angle = 2.0 * std::f64::consts::PI * i as f64 / n as f64;
+            (100.0 * angle.cos(), 100.0 * angle.sin())
+        })
+        .collect();
+    points
+        .iter()
+        .map(|a| {
+            points
+                .iter()
+                .map(|b| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt() as i32)
+                .collect()
+        })
+        .collect()
+}
+
+pub fn is_permutation(tour: &[u16], n: usize) -> bool {
+    let mut sorted = tour.to_vec();
+    sorted.sort();
+    sorted == (0..n as u16).collect::<Vec<u16>>()
+}
@@ -0,0 +1,170 @@
+use std::{error::Error, fmt::Display};
+
+use crate::helpers::tour_len;
+
+/// A validated tour, i.e. a permutation of the cities 0..n
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tour(Vec<u16>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TourError {
+    CityOutOfRange(u32),
+    DuplicateCity(u16),
+}
+
+impl Display for TourError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CityOutOfRange(city) => write!(f, "Tour Error: city {} out of range", city),
+            Self::DuplicateCity(city) => write!(f, "Tour Error: city {} visited twice", city),
+        }
+    }
+}
+
+impl Error for TourError {}
+
+impl Tour {
+    pub fn new(cities: Vec<u16>) -> Result<Self, TourError> {
+        let mut seen = vec![false; cities.len()];
+        for &city in &cities {
+            match seen.get_mut(city as usize) {
+                None => return Err(TourError::CityOutOfRange(city as u32)),
+                Some(true) => return Err(TourError::DuplicateCity(city)),
+                Some(visited) => *visited = true,
+            }
+        }
+        Ok(Self(cities))
+    }
+
+    /// Number of cities
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn cities(&self) -> &[u16] {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Vec<u16> {
+        self.0
+    }
+
+    pub fn tour_len(&self, distance_matrix: &[Vec<i32>]) -> i32 {
+        tour_len(&self.0, distance_matrix)
+    }
+
+    /// Directed edges in tour order, including the one closing the cycle
+    pub fn edges(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        let n = self.0.len();
+        (0..n).map(move |i| (self.0[i], self.0[(i + 1) % n]))
+    }
+
+    /// Reverses the direction of travel, keeping the first city in place
+    pub fn reverse(&mut self) {
+        if self.0.len() > 1 {
+            self.0[1..].reverse();
+        }
+    }
+
+    /// Rotates the tour so that it starts with city, does nothing for unknown cities
+    pub fn rotate_to(&mut self, city: u16) {
+        if let Some(pos) = self.0.iter().position(|&c| c == city) {
+            self.0.rotate_left(pos);
+        }
+    }
+
+    /// Same cycle starting with city 0, equal for all rotations of a tour.
+    /// Use this form for asymmetric instances, where the direction matters.
+    pub fn canonical_rotation(&self) -> Self {
+        let mut tour = self.clone();
+        tour.rotate_to(0);
+        tour
+    }
+
+    /// Same cycle starting with city 0 and travelled towards its smaller neighbour,
+    /// equal for all rotations and both directions of a tour
+    pub fn canonical(&self) -> Self {
+        let mut tour = self.canonical_rotation();
+        let n = tour.len();
+        if n > 2 && tour.0[1] > tour.0[n - 1] {
+            tour.reverse();
+        }
+        tour
+    }
+}
+
+impl AsRef<[u16]> for Tour {
+    fn as_ref(&self) -> &[u16] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<u16>> for Tour {
+    type Error = TourError;
+
+    fn try_from(cities: Vec<u16>) -> Result<Self, Self::Error> {
+        Tour::new(cities)
+    }
+}
+
+/// Converts the output of `parse_tour_file`, whose city ids are already shifted to start at 0
+impl TryFrom<Vec<u32>> for Tour {
+    type Error = TourError;
+
+    fn try_from(cities: Vec<u32>) -> Result<Self, Self::Error> {
+        let cities = cities
+            .into_iter()
+            .map(|city| u16::try_from(city).map_err(|_| TourError::CityOutOfRange(city)))
+            .collect::<Result<Vec<u16>, TourError>>()?;
+        Tour::new(cities)
+    }
+}
+
+impl From<Tour> for Vec<u16> {
+    fn from(tour: Tour) -> Self {
+        tour.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_tours() {
+        assert_eq!(Tour::new(vec![0, 2, 2]), Err(TourError::DuplicateCity(2)));
+        assert_eq!(Tour::new(vec![0, 3, 1]), Err(TourError::CityOutOfRange(3)));
+        assert_eq!(
+            Tour::try_from(vec![0u32, 70000]),
+            Err(TourError::CityOutOfRange(70000))
+        );
+        assert!(Tour::new(vec![]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn canonical_ignores_rotation_and_direction() {
+        let tour = Tour::new(vec![3, 1, 0, 4, 2]).unwrap();
+        let mut reversed = tour.clone();
+        reversed.reverse();
+        let mut rotated = tour.clone();
+        rotated.rotate_to(4);
+
+        assert_eq!(tour.canonical().cities(), &[0, 1, 3, 2, 4]);
+        assert_eq!(reversed.canonical(), tour.canonical());
+        assert_eq!(rotated.canonical(), tour.canonical());
+        assert_eq!(rotated.canonical_rotation(), tour.canonical_rotation());
+        assert_ne!(reversed.canonical_rotation(), tour.canonical_rotation());
+    }
+
+    #[test]
+    fn edges_close_the_cycle() {
+        let tour = Tour::new(vec![2, 0, 1]).unwrap();
+        let edges: Vec<(u16, u16)> = tour.edges().collect();
+        assert_eq!(edges, vec![(2, 0), (0, 1), (1, 2)]);
+        assert_eq!(Tour::new(vec![]).unwrap().tour_len(&[]), 0);
+    }
+}
@@ -0,0 +1,251 @@
+//! Distances between tours over the same cities.
+//!
+//! Positional metrics (Hamming, Kendall tau, swap, reversal) first rotate both tours to start
+//! with city 0 and return the smaller value over both directions of the second tour, so
+//! they do not depend on where a tour starts or which way it is travelled.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::helpers::cmp_permutations;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TourMetric {
+    /// Undirected edges of the first tour missing in the second one
+    Bond,
+    /// Directed edges of the first tour missing in the second one
+    Adjacency,
+    /// Approximate number of segment reversals (2-opt moves)
+    Reversal,
+    KendallTau,
+    Hamming,
+    /// Minimal number of pair swaps
+    Swap,
+}
+
+impl TourMetric {
+    pub const ALL: [TourMetric; 6] = [
+        TourMetric::Bond,
+        TourMetric::Adjacency,
+        TourMetric::Reversal,
+        TourMetric::KendallTau,
+        TourMetric::Hamming,
+        TourMetric::Swap,
+    ];
+
+    pub fn distance(&self, a: &[u16], b: &[u16]) -> u32 {
+        match self {
+            Self::Bond => bond_distance(a, b),
+            Self::Adjacency => adjacency_distance(a, b),
+            Self::Reversal => reversal_distance(a, b),
+            Self::KendallTau => kendall_tau_distance(a, b),
+            Self::Hamming => hamming_distance(a, b),
+            Self::Swap => swap_distance(a, b),
+        }
+    }
+
+    /// Name accepted by [`FromStr`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bond => "bond",
+            Self::Adjacency => "adjacency",
+            Self::Reversal => "reversal",
+            Self::KendallTau => "kendall",
+            Self::Hamming => "hamming",
+            Self::Swap => "swap",
+        }
+    }
+
+    /// Human readable unit, e.g. for plot axes
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Bond => "non-shared edges",
+            Self::Adjacency => "non-shared directed edges",
+            Self::Reversal => "reversals",
+            Self::KendallTau => "discordant pairs",
+            Self::Hamming => "differing positions",
+            Self::Swap => "pair swaps",
+        }
+    }
+}
+
+impl FromStr for TourMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bond" => Ok(Self::Bond),
+            "adjacency" => Ok(Self::Adjacency),
+            "reversal" => Ok(Self::Reversal),
+            "kendall" => Ok(Self::KendallTau),
+            "hamming" => Ok(Self::Hamming),
+            "swap" => Ok(Self::Swap),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+impl Display for TourMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Successor of every city in the tour
+fn successors(tour: &[u16]) -> Vec<u16> {
+    let mut next = vec![0; tour.len()];
+    for i in 0..tour.len() {
+        next[tour[i] as usize] = tour[(i + 1) % tour.len()];
+    }
+    next
+}
+
+pub fn bond_distance(a: &[u16], b: &[u16]) -> u32 {
+    let next = successors(b);
+    let mut prev = vec![0; b.len()];
+    for (city, &n) in next.iter().enumerate() {
+        prev[n as usize] = city as u16;
+    }
+
+    (0..a.len())
+        .filter(|&i| {
+            let (x, y) = (a[i], a[(i + 1) % a.len()]);
+            next[x as usize] != y && prev[x as usize] != y
+        })
+        .count() as u32
+}
+
+pub fn adjacency_distance(a: &[u16], b: &[u16]) -> u32 {
+    let next = successors(b);
+    (0..a.len())
+        .filter(|&i| next[a[i] as usize] != a[(i + 1) % a.len()])
+        .count() as u32
+}
+
+/// Upper bound on the number of reversals turning a into b, found by fixing one position
+/// after another. Half of [`bond_distance`] rounded up is a matching lower bound.
+pub fn reversal_distance(a: &[u16], b: &[u16]) -> u32 {
+    min_over_directions(a, b, |a, b| {
+        let mut p = relative_permutation(a, b);
+        let mut pos = vec![0; p.len()];
+        for (i, &x) in p.iter().enumerate() {
+            pos[x as usize] = i;
+        }
+
+        let mut count = 0;
+        for i in 0..p.len() {
+            if p[i] as usize != i {
+                let j = pos[i];
+                p[i..=j].reverse();
+                for k in i..=j {
+                    pos[p[k] as usize] = k;
+                }
+                count += 1;
+            }
+        }
+        count
+    })
+}
+
+/// Number of city pairs visited in different order
+pub fn kendall_tau_distance(a: &[u16], b: &[u16]) -> u32 {
+    min_over_directions(a, b, |a, b| {
+        let mut p = relative_permutation(a, b);
+        count_inversions(&mut p) as u32
+    })
+}
+
+pub fn hamming_distance(a: &[u16], b: &[u16]) -> u32 {
+    min_over_directions(a, b, |a, b| {
+        a.iter().zip(b).filter(|(x, y)| x != y).count() as u32
+    })
+}
+
+pub fn swap_distance(a: &[u16], b: &[u16]) -> u32 {
+    min_over_directions(a, b, cmp_permutations)
+}
+
+fn rotated_to_first(tour: &[u16]) -> Vec<u16> {
+    let mut tour = tour.to_vec();
+    if let Some(pos) = tour.iter().position(|&c| c == 0) {
+        tour.rotate_left(pos);
+    }
+    tour
+}
+
+fn min_over_directions<F: Fn(&[u16], &[u16]) -> u32>(a: &[u16], b: &[u16], metric: F) -> u32 {
+    let a = rotated_to_first(a);
+    let mut b = rotated_to_first(b);
+    let forward = metric(&a, &b);
+    if b.len() > 2 {
+        b[1..].reverse();
+    }
+    forward.min(metric(&a, &b))
+}
+
+/// Positions in b of the cities of a, the identity if both are equal
+fn relative_permutation(a: &[u16], b: &[u16]) -> Vec<u16> {
+    let mut pos_in_b = vec![0; b.len()];
+    for (i, &city) in b.iter().enumerate() {
+        pos_in_b[city as usize] = i as u16;
+    }
+    a.iter().map(|&city| pos_in_b[city as usize]).collect()
+}
+
+/// Merge sort counting inversions, sorts the slice
+fn count_inversions(p: &mut [u16]) -> u64 {
+    if p.len() < 2 {
+        return 0;
+    }
+    let mid = p.len() / 2;
+    let mut count = count_inversions(&mut p[..mid]) + count_inversions(&mut p[mid..]);
+
+    let mut merged = Vec::with_capacity(p.len());
+    let (mut i, mut j) = (0, mid);
+    while i < mid && j < p.len() {
+        if p[i] <= p[j] {
+            merged.push(p[i]);
+            i += 1;
+        } else {
+            merged.push(p[j]);
+            count += (mid - i) as u64;
+            j += 1;
+        }
+    }
+    merged.extend_from_slice(&p[i..mid]);
+    merged.extend_from_slice(&p[j..]);
+    p.copy_from_slice(&merged);
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_and_reversal_are_free() {
+        let a = [0, 1, 2, 3, 4, 5];
+        let rotated = [3, 4, 5, 0, 1, 2];
+        let reversed = [0, 5, 4, 3, 2, 1];
+
+        for metric in TourMetric::ALL {
+            assert_eq!(metric.distance(&a, &rotated), 0, "{}", metric);
+            if metric != TourMetric::Adjacency {
+                assert_eq!(metric.distance(&a, &reversed), 0, "{}", metric);
+            }
+        }
+        assert_eq!(adjacency_distance(&a, &reversed), 6);
+    }
+
+    #[test]
+    fn two_opt_move_distances() {
+        let a = [0, 1, 2, 3, 4, 5, 6, 7];
+        //reverse positions 2..=5
+        let b = [0, 1, 5, 4, 3, 2, 6, 7];
+
+        assert_eq!(bond_distance(&a, &b), 2);
+        assert_eq!(reversal_distance(&a, &b), 1);
+        assert_eq!(kendall_tau_distance(&a, &b), 6);
+        assert_eq!(hamming_distance(&a, &b), 4);
+        assert_eq!(swap_distance(&a, &b), 2);
+    }
+}
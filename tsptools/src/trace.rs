@@ -0,0 +1,52 @@
+//! Optional recording of the moves accepted by the local searches
+
+/// Neighbourhood move applied to a tour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind {
+    /// Swap of the cities at positions `i` and `j`
+    Swap,
+    /// Reversal of positions `[i+1, j]`
+    TwoOpt,
+}
+
+/// One accepted move of a local search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub kind: MoveKind,
+    pub i: usize,
+    pub j: usize,
+    /// Change of the tour length, negative for improving moves
+    pub delta: i32,
+    /// Tour length after the move
+    pub len: i32,
+    /// Number of the move, starting at 1
+    pub step: u64,
+}
+
+/// Receives the accepted moves. `()` discards them and compiles away, `Vec<Move>` keeps them.
+pub trait Trace {
+    /// False if the moves are discarded, so the searches can skip building them
+    const ENABLED: bool = true;
+
+    fn accepted(&mut self, step: Move);
+}
+
+impl Trace for () {
+    const ENABLED: bool = false;
+
+    #[inline(always)]
+    fn accepted(&mut self, _: Move) {}
+}
+
+impl Trace for Vec<Move> {
+    fn accepted(&mut self, step: Move) {
+        self.push(step);
+    }
+}
+
+/// Tour length before every move and after the last one, the descent curve of the search
+pub fn descent_curve(starting_len: i32, moves: &[Move]) -> Vec<i32> {
+    std::iter::once(starting_len)
+        .chain(moves.iter().map(|step| step.len))
+        .collect()
+}